@@ -0,0 +1,21 @@
+-- Both conflicting promises are kept. Original promise fields are nullable as
+-- transactions learned from bundles or peers only come with a receipt
+CREATE TABLE IF NOT EXISTS equivocations (
+    id SERIAL NOT NULL,
+    tx_id CHAR(43) NOT NULL,
+    original_signature BYTEA NOT NULL,
+    size BIGINT NOT NULL,
+    fee VARCHAR(40) NOT NULL,
+    currency VARCHAR(40) NOT NULL,
+    block_promised BYTEA NOT NULL,
+    bundler_signature BYTEA NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    original_size BIGINT,
+    original_fee VARCHAR(40),
+    original_currency VARCHAR(40),
+    original_block_promised BYTEA,
+    original_bundler_signature BYTEA,
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX equivocations_tx_id_idx ON equivocations(tx_id);
@@ -0,0 +1,5 @@
+ALTER TABLE transactions
+    DROP COLUMN size,
+    DROP COLUMN fee,
+    DROP COLUMN currency,
+    DROP COLUMN bundler_signature;
@@ -0,0 +1,7 @@
+-- Promise fields are nullable as transactions learned from bundles or peers
+-- only come with a receipt
+ALTER TABLE transactions
+    ADD COLUMN size BIGINT,
+    ADD COLUMN fee VARCHAR(40),
+    ADD COLUMN currency VARCHAR(40),
+    ADD COLUMN bundler_signature BYTEA;
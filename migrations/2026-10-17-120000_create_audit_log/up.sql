@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+    seq BIGINT NOT NULL,
+    tx_id CHAR(43) NOT NULL,
+    request TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    prev_hash CHAR(43) NOT NULL,
+    hash CHAR(43) NOT NULL,
+    PRIMARY KEY (seq)
+);
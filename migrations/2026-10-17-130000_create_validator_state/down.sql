@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS scheduled_epochs;
+DROP TABLE IF EXISTS validator_state;
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS validator_state (
+    id SMALLINT NOT NULL CHECK (id = 1),
+    current_block BYTEA NOT NULL,
+    current_epoch BYTEA NOT NULL,
+    role SMALLINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS scheduled_epochs (
+    seq BYTEA NOT NULL,
+    height BYTEA NOT NULL,
+    role SMALLINT NOT NULL,
+    PRIMARY KEY (seq)
+);
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS receipt_forwarding;
+DROP TABLE IF EXISTS leader_reports;
+DROP TABLE IF EXISTS receipt_aggregates;
+DROP TABLE IF EXISTS receipts;
@@ -0,0 +1,43 @@
+-- Receipts other validators forwarded to the leader of an epoch
+CREATE TABLE IF NOT EXISTS receipts (
+    tx_id CHAR(43) NOT NULL,
+    validator CHAR(43) NOT NULL,
+    epoch BYTEA NOT NULL,
+    block_promised BYTEA NOT NULL,
+    size BIGINT NOT NULL,
+    fee VARCHAR NOT NULL,
+    currency VARCHAR NOT NULL,
+    bundler_signature BYTEA NOT NULL,
+    signature BYTEA NOT NULL,
+    received_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tx_id, validator)
+);
+
+CREATE INDEX receipts_epoch_idx ON receipts(epoch);
+
+-- Receipts per transaction, cosigners disagreeing on the promise are conflicting
+CREATE TABLE IF NOT EXISTS receipt_aggregates (
+    epoch BYTEA NOT NULL,
+    tx_id CHAR(43) NOT NULL,
+    block_promised BYTEA NOT NULL,
+    cosigners INTEGER NOT NULL,
+    conflicting BOOLEAN NOT NULL,
+    PRIMARY KEY (epoch, tx_id)
+);
+
+CREATE TABLE IF NOT EXISTS leader_reports (
+    epoch BYTEA NOT NULL,
+    tx_count BIGINT NOT NULL,
+    receipt_count BIGINT NOT NULL,
+    conflicting_count BIGINT NOT NULL,
+    participants TEXT[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (epoch)
+);
+
+-- Last audit log entry forwarded to the leader, single row with `id` 1
+CREATE TABLE IF NOT EXISTS receipt_forwarding (
+    id SMALLINT NOT NULL CHECK (id = 1),
+    last_seq BIGINT NOT NULL,
+    PRIMARY KEY (id)
+);
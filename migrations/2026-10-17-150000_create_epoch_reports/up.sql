@@ -0,0 +1,7 @@
+-- Signed end-of-epoch reports, `report` is the JSON document as signed
+CREATE TABLE IF NOT EXISTS epoch_reports (
+    epoch BYTEA NOT NULL,
+    report TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (epoch)
+);
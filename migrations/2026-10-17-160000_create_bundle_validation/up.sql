@@ -0,0 +1,9 @@
+-- Position in the bundler's Arweave history bundle validation resumes from,
+-- single row with `id` 1
+CREATE TABLE IF NOT EXISTS bundle_validation (
+    id SMALLINT NOT NULL CHECK (id = 1),
+    cursor TEXT NOT NULL,
+    block BYTEA NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id)
+);
@@ -0,0 +1,9 @@
+-- Slash proposals for cosigned transactions the bundler didn't include in
+-- time, at most one per transaction
+CREATE TABLE IF NOT EXISTS slash_proposals (
+    tx_id CHAR(43) NOT NULL,
+    proposal TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    submitted_at TIMESTAMP,
+    PRIMARY KEY (tx_id)
+);
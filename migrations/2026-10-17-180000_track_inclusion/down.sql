@@ -0,0 +1,3 @@
+ALTER TABLE transactions
+    DROP COLUMN late,
+    DROP COLUMN missing;
@@ -0,0 +1,5 @@
+-- Transactions not included by the promised block, `late` ones were included
+-- afterwards at `block_actual`
+ALTER TABLE transactions
+    ADD COLUMN late BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN missing BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS bundler_key_history;
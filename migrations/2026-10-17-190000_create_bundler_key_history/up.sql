@@ -0,0 +1,11 @@
+-- Bundler keys and the block heights the validator saw them in use at,
+-- `valid_until` is NULL for the key the bundler currently signs with
+CREATE TABLE IF NOT EXISTS bundler_key_history (
+    id BIGSERIAL NOT NULL,
+    address VARCHAR NOT NULL,
+    key_type VARCHAR NOT NULL,
+    key BYTEA NOT NULL,
+    valid_from BYTEA NOT NULL,
+    valid_until BYTEA,
+    PRIMARY KEY (id)
+);
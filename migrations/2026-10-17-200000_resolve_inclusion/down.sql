@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS transactions_unresolved_idx;
+
+ALTER TABLE transactions
+    DROP COLUMN block_promised_height,
+    DROP COLUMN resolved;
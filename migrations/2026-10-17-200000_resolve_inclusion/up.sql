@@ -0,0 +1,21 @@
+-- Promised block as a number, so that due transactions can be selected and
+-- ordered by the database. Blocks are stored as 16 little-endian bytes,
+-- heights fit in the lower 8
+ALTER TABLE transactions
+    ADD COLUMN block_promised_height BIGINT NOT NULL GENERATED ALWAYS AS (
+        (get_byte(block_promised, 0)::BIGINT)
+        + (get_byte(block_promised, 1)::BIGINT << 8)
+        + (get_byte(block_promised, 2)::BIGINT << 16)
+        + (get_byte(block_promised, 3)::BIGINT << 24)
+        + (get_byte(block_promised, 4)::BIGINT << 32)
+        + (get_byte(block_promised, 5)::BIGINT << 40)
+        + (get_byte(block_promised, 6)::BIGINT << 48)
+        + (get_byte(block_promised, 7)::BIGINT << 56)
+    ) STORED,
+    -- Inclusion is final and won't be looked up again
+    ADD COLUMN resolved BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE transactions SET resolved = TRUE WHERE validated OR late;
+
+CREATE INDEX transactions_unresolved_idx ON transactions (block_promised_height)
+    WHERE NOT resolved;
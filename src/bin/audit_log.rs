@@ -0,0 +1,95 @@
+use std::{
+    fs::{self, File},
+    io::{stdout, BufWriter, Write},
+    process,
+};
+
+use clap::{Parser, Subcommand};
+use diesel::{Connection, PgConnection};
+
+use validator::{audit_log, database::models::AuditLogEntry};
+
+#[derive(Subcommand)]
+enum Command {
+    /// Export audit log as JSON lines, oldest entry first
+    Export {
+        /// Path to output file
+        ///
+        /// When not provided, entries are written to stdout.
+        #[clap(short = 'o', long)]
+        output: Option<String>,
+    },
+    /// Verify audit log hash chain
+    Verify {
+        /// Path to previously exported audit log
+        ///
+        /// When not provided, log is read from the database.
+        #[clap(short = 'i', long)]
+        input: Option<String>,
+    },
+}
+
+#[derive(Parser)]
+struct Args {
+    /// Database connection URL
+    #[clap(long, env)]
+    database_url: Option<String>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+fn load_from_db(database_url: &Option<String>) -> Vec<AuditLogEntry> {
+    let database_url = database_url
+        .as_ref()
+        .expect("Database URL is required to read the audit log from the database");
+    let conn = PgConnection::establish(database_url).expect("Failed to connect to database");
+    audit_log::load(&conn).expect("Failed to load audit log")
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+
+    let args = Args::parse();
+
+    match args.command {
+        Command::Export { ref output } => {
+            let entries = load_from_db(&args.database_url);
+            let mut writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path).expect("Failed to create output file")),
+                None => Box::new(stdout()),
+            };
+            let mut writer = BufWriter::new(&mut writer);
+            for entry in entries {
+                serde_json::to_writer(&mut writer, &entry).expect("Failed to write entry");
+                writeln!(writer).expect("Failed to write entry");
+            }
+        }
+        Command::Verify { ref input } => {
+            let entries = match input {
+                Some(path) => fs::read_to_string(path)
+                    .expect("Failed to read audit log file")
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| serde_json::from_str(line).expect("Failed to parse entry"))
+                    .collect(),
+                None => load_from_db(&args.database_url),
+            };
+
+            match audit_log::verify(&entries) {
+                Ok(()) => match entries.last() {
+                    Some(last) => println!(
+                        r#"{{"valid":true,"entries":{},"head":"{}"}}"#,
+                        entries.len(),
+                        last.hash
+                    ),
+                    None => println!(r#"{{"valid":true,"entries":0}}"#),
+                },
+                Err(err) => {
+                    eprintln!("Audit log verification failed: {}", err);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+}
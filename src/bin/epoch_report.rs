@@ -0,0 +1,109 @@
+use std::{
+    fs::{self, File},
+    io::{stdout, Write},
+    process,
+};
+
+use clap::{Parser, Subcommand};
+use diesel::{Connection, PgConnection};
+
+use validator::epoch_report::{self, EpochReport};
+
+#[derive(Subcommand)]
+enum Command {
+    /// Export signed report of an epoch as JSON
+    Export {
+        /// Epoch to export the report of
+        #[clap(short = 'e', long)]
+        epoch: u128,
+
+        /// Path to output file
+        ///
+        /// When not provided, report is written to stdout.
+        #[clap(short = 'o', long)]
+        output: Option<String>,
+    },
+    /// Verify signature of an exported report, no database or network needed
+    Verify {
+        /// Path to previously exported report
+        #[clap(short = 'i', long)]
+        input: String,
+
+        /// Address of the validator expected to have signed the report
+        #[clap(long)]
+        validator: Option<String>,
+    },
+}
+
+#[derive(Parser)]
+struct Args {
+    /// Database connection URL
+    #[clap(long, env)]
+    database_url: Option<String>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+
+    let args = Args::parse();
+
+    match args.command {
+        Command::Export { epoch, ref output } => {
+            let database_url = args
+                .database_url
+                .as_ref()
+                .expect("Database URL is required to export a report");
+            let conn =
+                PgConnection::establish(database_url).expect("Failed to connect to database");
+            let report = match epoch_report::load_report(&conn, epoch)
+                .expect("Failed to load epoch report")
+            {
+                Some(report) => report,
+                None => {
+                    eprintln!("No report for epoch {}", epoch);
+                    process::exit(1);
+                }
+            };
+
+            let mut writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path).expect("Failed to create output file")),
+                None => Box::new(stdout()),
+            };
+            serde_json::to_writer_pretty(&mut writer, &report).expect("Failed to write report");
+            writeln!(writer).expect("Failed to write report");
+        }
+        Command::Verify {
+            ref input,
+            ref validator,
+        } => {
+            let report: EpochReport = serde_json::from_str(
+                &fs::read_to_string(input).expect("Failed to read report file"),
+            )
+            .expect("Failed to parse report");
+
+            if let Some(validator) = validator {
+                if &report.validator != validator {
+                    eprintln!(
+                        "Report verification failed: signed by {}, expected {}",
+                        report.validator, validator
+                    );
+                    process::exit(1);
+                }
+            }
+
+            match report.verify() {
+                Ok(()) => println!(
+                    r#"{{"valid":true,"epoch":"{}","validator":"{}","tx_count":{}}}"#,
+                    report.epoch, report.validator, report.tx_count
+                ),
+                Err(err) => {
+                    eprintln!("Report verification failed: {}", err);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs,
+    os::unix::{fs::PermissionsExt, net::UnixListener},
+    path::PathBuf,
+    sync::Arc,
+    thread,
+};
+
+use clap::Parser;
+use env_logger::Env;
+use log::{error, info};
+
+use validator::{
+    key_manager::{remote::serve_connection, split_jwk},
+    keystore,
+};
+
+/// Holds validator private key and signs on behalf of the validator server
+#[derive(Parser)]
+struct Args {
+    /// Path to JWK file or encrypted keystore holding validator private key
+    #[clap(long, env = "VALIDATOR_KEY")]
+    validator_key: String,
+
+    /// Path to file holding the password for encrypted validator keystore
+    #[clap(long, env = "VALIDATOR_KEY_PASSWORD_FILE")]
+    validator_key_password_file: Option<String>,
+
+    /// Password for encrypted validator keystore
+    #[clap(long, env = "VALIDATOR_KEY_PASSWORD", hide_env_values = true)]
+    validator_key_password: Option<String>,
+
+    /// Path of the Unix socket to listen on
+    #[clap(long, env = "SIGNER_SOCKET")]
+    socket: PathBuf,
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+
+    env_logger::init_from_env(Env::default().default_filter_or("info"));
+
+    let args = Args::parse();
+
+    let password = keystore::resolve_password(
+        args.validator_key_password_file.as_deref(),
+        args.validator_key_password.as_deref(),
+    )
+    .expect("Failed to read validator key password");
+    let jwk = keystore::load_key(&args.validator_key, password.as_deref())
+        .expect("Failed to load validator key");
+    let (validator_private, _, validator_address) = split_jwk(&jwk);
+    let validator_private = Arc::new(validator_private);
+
+    // Remove socket left behind by previous run
+    if args.socket.exists() {
+        fs::remove_file(&args.socket).expect("Failed to remove stale socket");
+    }
+
+    let listener = UnixListener::bind(&args.socket).expect("Failed to bind signer socket");
+    fs::set_permissions(&args.socket, fs::Permissions::from_mode(0o600))
+        .expect("Failed to restrict signer socket permissions");
+
+    info!(
+        "Signing for {} on {}",
+        validator_address,
+        args.socket.display()
+    );
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                error!("Failed to accept connection: {}", err);
+                continue;
+            }
+        };
+
+        let validator_private = validator_private.clone();
+        thread::spawn(move || {
+            if let Err(err) = serve_connection(&mut stream, &validator_private) {
+                error!("Failed to serve connection: {}", err);
+            }
+        });
+    }
+}
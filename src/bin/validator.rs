@@ -1,24 +1,41 @@
 use clap::Parser;
-use data_encoding::{DecodeError, BASE64URL_NOPAD};
 use diesel::{
     r2d2::{self, ConnectionManager},
     PgConnection,
 };
 use env_logger::Env;
-use jsonwebkey::{JsonWebKey, Key, PublicExponent, RsaPublic};
-use log::info;
-use std::{fs, net::SocketAddr, str::FromStr};
+use log::{error, info};
+use std::{
+    fs, net::SocketAddr, num::NonZeroUsize, path::PathBuf, str::FromStr, thread, time::Duration,
+};
 use sysinfo::{System, SystemExt};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::{mpsc, oneshot},
+};
 use url::Url;
 
+#[cfg(feature = "pkcs11")]
+use validator::key_manager::pkcs11::{self, Pkcs11Config, Pkcs11KeyManager};
 use validator::{
     bundler::BundlerConfig,
+    bundler_keys::{self, run_bundler_key_refresh, BundlerKeys},
+    cron::scheduler::{JobRegistry, JobsConfig},
+    events::{log_events, EventsAccess},
     hardware::HardwareCheck,
     http::reqwest::ReqwestClient,
-    key_manager::{InMemoryKeyManager, InMemoryKeyManagerConfig},
+    key_manager::{self, remote::RemoteKeyManager, InMemoryKeyManager},
+    key_rotation::{run_key_reloader, ReloadReply},
+    keystore,
+    readiness::ReadinessConfig,
+    signing_policy::{SigningPolicies, SigningPolicyConfig},
+    signing_pool::SigningPool,
+};
+use validator::{context::AppContext, state::restore_state};
+use validator::{
+    cron::run_crons,
+    server::{routes::admin::AdminToken, run_admin_server, run_server},
 };
-use validator::{context::AppContext, state::generate_state};
-use validator::{cron::run_crons, server::run_server};
 
 #[derive(Clone, Debug, Parser)]
 struct CliOpts {
@@ -42,9 +59,82 @@ struct CliOpts {
     #[clap(long, env = "BUNDLER_URL")]
     bundler_url: Url,
 
-    /// Path to JWK file holding validator private key
+    /// Path to JWK file or encrypted keystore holding validator private key
     #[clap(long, env = "VALIDATOR_KEY")]
-    validator_key: String,
+    #[cfg_attr(
+        not(feature = "pkcs11"),
+        clap(required_unless_present = "signer-socket")
+    )]
+    #[cfg_attr(
+        feature = "pkcs11",
+        clap(required_unless_present_any = &["signer-socket", "pkcs11-module"])
+    )]
+    validator_key: Option<String>,
+
+    /// Path to file holding the password for encrypted validator keystore
+    #[clap(long, env = "VALIDATOR_KEY_PASSWORD_FILE")]
+    validator_key_password_file: Option<String>,
+
+    /// Password for encrypted validator keystore
+    #[clap(long, env = "VALIDATOR_KEY_PASSWORD", hide_env_values = true)]
+    validator_key_password: Option<String>,
+
+    /// Path to Unix socket of the signer holding validator private key
+    ///
+    /// When provided, validator private key is never loaded into this process.
+    #[clap(long, env = "SIGNER_SOCKET")]
+    signer_socket: Option<PathBuf>,
+
+    /// Timeout in seconds for requests to the signer
+    #[clap(long, env = "SIGNER_TIMEOUT", default_value = "5")]
+    signer_timeout: u64,
+
+    /// Path to PKCS#11 module used to sign with validator key held by a token
+    #[cfg(feature = "pkcs11")]
+    #[clap(long, env = "PKCS11_MODULE")]
+    pkcs11_module: Option<PathBuf>,
+
+    /// Slot of the token holding validator key
+    #[cfg(feature = "pkcs11")]
+    #[clap(long, env = "PKCS11_SLOT", default_value = "0")]
+    pkcs11_slot: u64,
+
+    /// Label of validator private key on the token
+    #[cfg(feature = "pkcs11")]
+    #[clap(long, env = "PKCS11_KEY_LABEL", default_value = "validator")]
+    pkcs11_key_label: String,
+
+    /// Path to file holding the token user PIN
+    #[cfg(feature = "pkcs11")]
+    #[clap(long, env = "PKCS11_PIN_FILE")]
+    pkcs11_pin_file: Option<String>,
+
+    /// Token user PIN
+    #[cfg(feature = "pkcs11")]
+    #[clap(long, env = "PKCS11_PIN", hide_env_values = true)]
+    pkcs11_pin: Option<String>,
+
+    /// Seconds the previous validator key keeps signing after a key reload
+    #[clap(long, env = "KEY_OVERLAP", default_value = "600")]
+    key_overlap: u64,
+
+    /// Listen address for the admin server, not started when not provided
+    ///
+    /// Only loopback addresses are allowed unless an admin token is set.
+    #[clap(long, env = "ADMIN_LISTEN")]
+    admin_listen: Option<SocketAddr>,
+
+    /// Token admin requests must carry as `Authorization: Bearer <token>`
+    #[clap(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Seconds between checks for a new bundler key
+    #[clap(long, env = "BUNDLER_KEY_REFRESH", default_value = "60")]
+    bundler_key_refresh: u64,
+
+    /// Seconds the previous bundler key is accepted after the bundler rotates it
+    #[clap(long, env = "BUNDLER_KEY_GRACE", default_value = "600")]
+    bundler_key_grace: u64,
 
     #[clap(long, env = "ARWEAVE_URL")]
     arweave_url: Option<Url>,
@@ -58,6 +148,41 @@ struct CliOpts {
         default_value = "http://localhost:3000"
     )]
     contract_gateway_url: Url,
+
+    /// Threads signing and verifying signatures, defaults to the number of CPUs
+    #[clap(long, env = "SIGNING_WORKERS")]
+    signing_workers: Option<NonZeroUsize>,
+
+    /// Sign requests waiting for a signing thread before new ones are turned away
+    #[clap(long, env = "SIGNING_QUEUE", default_value = "256")]
+    signing_queue: NonZeroUsize,
+
+    /// Seconds clients turned away because of a full signing queue should wait
+    #[clap(long, env = "SIGNING_RETRY_AFTER", default_value = "1")]
+    signing_retry_after: u64,
+
+    /// Sign requests accepted in a single batch
+    #[clap(long, env = "MAX_BATCH_SIZE", default_value = "100")]
+    max_batch_size: usize,
+
+    /// Seconds since the last contract or network sync after which signing stops
+    #[clap(long, env = "MAX_SYNC_AGE", default_value = "300")]
+    max_sync_age: u64,
+
+    /// Blocks the network may move on since the last contract sync before signing stops
+    #[clap(long, env = "MAX_BLOCK_LAG", default_value = "5")]
+    max_block_lag: u128,
+
+    /// Path to JSON file with signing policy rules
+    ///
+    /// When not provided, only the default promise window is enforced.
+    #[clap(long, env = "SIGNING_POLICY")]
+    signing_policy: Option<String>,
+
+    /// Path to JSON file overriding interval, timeout, jitter or maximum
+    /// backoff of cron jobs, in seconds, by job name
+    #[clap(long, env = "CRON_CONFIG")]
+    cron_config: Option<String>,
 }
 
 // TODO: merge config should return own type as returned arweave_url can never be None
@@ -77,68 +202,60 @@ fn merge_configs(config: CliOpts, bundler_config: BundlerConfig) -> CliOpts {
     }
 }
 
-fn public_only_jwk_from_rsa_n(encoded_n: &str) -> Result<JsonWebKey, DecodeError> {
-    Ok(JsonWebKey::new(Key::RSA {
-        public: RsaPublic {
-            e: PublicExponent,
-            n: BASE64URL_NOPAD.decode(encoded_n.as_bytes())?.into(),
-        },
-        private: None,
-    }))
-}
-
-struct Keys(JsonWebKey, JsonWebKey);
-
-impl InMemoryKeyManagerConfig for Keys {
-    fn bundler_jwk(&self) -> &JsonWebKey {
-        &self.0
-    }
-
-    fn validator_jwk(&self) -> &JsonWebKey {
-        &self.1
-    }
-}
-
-#[async_trait::async_trait]
-pub trait IntoAsync<T> {
-    async fn into_async(&self) -> T;
-}
-
-#[async_trait::async_trait]
-impl IntoAsync<AppContext> for CliOpts {
-    async fn into_async(&self) -> AppContext {
-        let fmt_bundler_url: String = self.bundler_url.to_string().replace(&['\"', '\''][..], "");
-        dbg!(&fmt_bundler_url);
-
-        // FIXME: use genrics to define HTTP client instead of using reqwest directly
-        let n_response = reqwest::get(format!("{}public", fmt_bundler_url))
-            .await
-            .expect("Couldn't get public key from bundler")
-            .text()
-            .await
-            .expect("Couldn't parse public key response from bundler");
-
-        let bundler_jwk =
-            public_only_jwk_from_rsa_n(&n_response).expect("Failed to decode bundler key");
-        let validator_jwk: JsonWebKey = {
-            let file = fs::read_to_string(&self.validator_key).unwrap();
-            file.parse().unwrap()
-        };
-
-        let key_manager = InMemoryKeyManager::new(&Keys(bundler_jwk, validator_jwk));
-        let state = generate_state();
-
+impl CliOpts {
+    fn into_context<KeyManager>(
+        &self,
+        key_manager: KeyManager,
+    ) -> AppContext<ReqwestClient, KeyManager>
+    where
+        KeyManager: key_manager::KeyManager,
+    {
         let connection_mgr = ConnectionManager::<PgConnection>::new(&self.database_url);
 
         let pool = r2d2::Pool::builder()
             .build(connection_mgr)
             .expect("Failed to create database connection pool.");
 
+        // Not signing on restored state before it is synced
+        let state = restore_state(&pool.get().expect("Failed to get database connection"))
+            .expect("Failed to restore validator state");
+        info!(
+            "Restored state: block={}, epoch={}, role={:?}",
+            state.current_block(),
+            state.current_epoch(),
+            state.role()
+        );
+
         let arweave_url = match &self.arweave_url {
             Some(url) => url,
             None => unreachable!(),
         };
 
+        let signing_policy_config: SigningPolicyConfig = match &self.signing_policy {
+            Some(path) => {
+                let file = fs::read_to_string(path).expect("Failed to read signing policy file");
+                serde_json::from_str(&file).expect("Failed to parse signing policy file")
+            }
+            None => SigningPolicyConfig::default(),
+        };
+
+        let jobs_config: JobsConfig = match &self.cron_config {
+            Some(path) => {
+                let file = fs::read_to_string(path).expect("Failed to read cron config file");
+                serde_json::from_str(&file).expect("Failed to parse cron config file")
+            }
+            None => JobsConfig::default(),
+        };
+
+        let signing_workers = self.signing_workers.unwrap_or_else(|| {
+            thread::available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap())
+        });
+        let signing_pool = SigningPool::new(
+            signing_workers.get(),
+            self.signing_queue.get(),
+            Duration::from_secs(self.signing_retry_after),
+        );
+
         AppContext::new(
             key_manager,
             pool,
@@ -148,6 +265,14 @@ impl IntoAsync<AppContext> for CliOpts {
             arweave_url,
             &self.bundler_url,
             &self.contract_gateway_url,
+            SigningPolicies::from(signing_policy_config),
+            signing_pool,
+            ReadinessConfig {
+                max_sync_age: Duration::from_secs(self.max_sync_age),
+                max_block_lag: self.max_block_lag,
+            },
+            self.max_batch_size,
+            JobRegistry::new(jobs_config),
         )
     }
 }
@@ -169,37 +294,138 @@ fn main() -> () {
         let http_client = ReqwestClient::new(reqwest::Client::new());
         let app_config = CliOpts::parse();
         let bundler_config =
-            BundlerConfig::fetch_config(http_client, &app_config.bundler_url).await;
+            BundlerConfig::fetch_config(http_client.clone(), &app_config.bundler_url).await;
         let config = merge_configs(app_config, bundler_config);
-        let ctx = config.into_async().await;
-
-        if !config.no_cron {
-            info!("Running with cron");
-            tokio::task::spawn_local(run_crons(ctx.clone()));
-        };
+        let bundler_key = bundler_keys::fetch_bundler_key(&http_client, &config.bundler_url)
+            .await
+            .expect("Failed to fetch bundler key");
+        info!("Bundler signs with {:?} key", bundler_key.key_type());
+        // Shared by all key managers, so reloaded validator keys see rotated bundler keys
+        let bundler_keys = BundlerKeys::with_key(bundler_key);
+        info!("Bundler address {}", bundler_keys.address());
+
+        #[cfg(feature = "pkcs11")]
+        if let Some(module) = &config.pkcs11_module {
+            info!("Signing with PKCS#11 token in slot {}", config.pkcs11_slot);
+            let pkcs11 = pkcs11::load_module(module).expect("Failed to load PKCS#11 module");
+            let slot = config.pkcs11_slot;
+            let key_label = config.pkcs11_key_label.clone();
+            let pin_file = config.pkcs11_pin_file.clone();
+            let pin = config.pkcs11_pin.clone();
+            let keys = bundler_keys.clone();
+            let load = move || {
+                let pin = keystore::resolve_password(pin_file.as_deref(), pin.as_deref())
+                    .map_err(|err| err.to_string())?
+                    .ok_or_else(|| "PKCS#11 PIN is required".to_string())?;
+                let config = Pkcs11Config {
+                    slot,
+                    pin: String::from_utf8(pin).map_err(|err| err.to_string())?,
+                    key_label: key_label.clone(),
+                };
+                Pkcs11KeyManager::open(&pkcs11, &config, keys.clone())
+                    .map_err(|err| err.to_string())
+            };
+            let key_manager = load().expect("Failed to open validator key on PKCS#11 token");
+            let ctx = config.into_context(key_manager);
+            return run(&config, ctx, bundler_keys, load).await;
+        }
 
-        if !config.no_server {
-            info!("Running with server");
-            run_server(ctx.clone()).await.unwrap()
-        };
+        // Key is loaded the same way on start up and on every reload, so that
+        // a replaced key file or a restarted signer is picked up
+        match (config.signer_socket.clone(), config.validator_key.clone()) {
+            (Some(socket), _) => {
+                info!("Signing with remote signer at {}", socket.display());
+                let timeout = Duration::from_secs(config.signer_timeout);
+                let keys = bundler_keys.clone();
+                let load = move || {
+                    RemoteKeyManager::connect(&socket, timeout, keys.clone())
+                        .map_err(|err| err.to_string())
+                };
+                let key_manager = load().expect("Failed to connect to signer");
+                let ctx = config.into_context(key_manager);
+                run(&config, ctx, bundler_keys, load).await
+            }
+            (None, Some(validator_key)) => {
+                let password_file = config.validator_key_password_file.clone();
+                let password = config.validator_key_password.clone();
+                let keys = bundler_keys.clone();
+                let load = move || {
+                    let password =
+                        keystore::resolve_password(password_file.as_deref(), password.as_deref())
+                            .map_err(|err| err.to_string())?;
+                    let validator_jwk = keystore::load_key(&validator_key, password.as_deref())
+                        .map_err(|err| err.to_string())?;
+                    Ok(InMemoryKeyManager::with_bundler_keys(
+                        keys.clone(),
+                        &validator_jwk,
+                    ))
+                };
+                let key_manager = load().expect("Failed to load validator key");
+                let ctx = config.into_context(key_manager);
+                run(&config, ctx, bundler_keys, load).await
+            }
+            (None, None) => unreachable!(),
+        }
     });
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::public_only_jwk_from_rsa_n;
-
-    #[test]
-    fn when_building_jwk_from_encoded_public_key_then_serialized_n_matches() {
-        let encoded_n = "sq9JbppKLlAKtQwalfX5DagnGMlTirditXk7y4jgoeA7DEM0Z6cVPE5xMQ9kz_T9VppP6BFHtHyZCZODercEVWipzkr36tfQkR5EDGUQyLivdxUzbWgVkzw7D27PJEa4cd1Uy6r18rYLqERgbRvAZph5YJZmpSJk7r3MwnQquuktjvSpfCLFwSxP1w879-ss_JalM9ICzRi38henONio8gll6GV9-omrWwRMZer_15bspCK5txCwpY137nfKwKD5YBAuzxxcj424M7zlSHlsafBwaRwFbf8gHtW03iJER4lR4GxeY0WvnYaB3KDISHQp53a9nlbmiWO5WcHHYsR83OT2eJ0Pl3RWA-_imk_SNwGQTCjmA6tf_UVwL8HzYS2iyuu85b7iYK9ZQoh8nqbNC6qibICE4h9Fe3bN7AgitIe9XzCTOXDfMr4ahjC8kkqJ1z4zNAI6-Leei_Mgd8JtZh2vqFNZhXK0lSadFl_9Oh3AET7tUds2E7s-6zpRPd9oBZu6-kNuHDRJ6TQhZSwJ9ZO5HYsccb_G_1so72aXJymR9ggJgWr4J3bawAYYnqmvmzGklYOlE_5HVnMxf-UxpT7ztdsHbc9QEH6W2bzwxbpjTczEZs3JCCB3c-NewNHsj9PYM3b5tTlTNP9kNAwPZHWpt11t79LuNkNGt9LfOek";
-
-        let jwk = public_only_jwk_from_rsa_n(encoded_n).expect("Failed to decode public key");
+/// Request validator key reload on every SIGHUP
+async fn reload_on_sighup(reloads: mpsc::Sender<ReloadReply>) {
+    let mut hangups = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+    while hangups.recv().await.is_some() {
+        info!("Received SIGHUP, reloading validator key");
+        // Outcome is logged by the reloader
+        let (reply, _) = oneshot::channel();
+        if reloads.send(reply).await.is_err() {
+            break;
+        }
+    }
+}
 
-        let json_str = serde_json::to_string(&jwk).unwrap();
+async fn run<KeyManager, Load>(
+    config: &CliOpts,
+    ctx: AppContext<ReqwestClient, KeyManager>,
+    bundler_keys: BundlerKeys,
+    load: Load,
+) where
+    KeyManager: key_manager::KeyManager + Send + Sync + 'static,
+    Load: Fn() -> Result<KeyManager, String> + 'static,
+{
+    tokio::task::spawn_local(log_events(ctx.events().subscribe()));
+
+    tokio::task::spawn_local(run_bundler_key_refresh(
+        ctx.clone(),
+        bundler_keys,
+        Duration::from_secs(config.bundler_key_refresh),
+        Duration::from_secs(config.bundler_key_grace),
+    ));
+
+    let (reloads, requests) = mpsc::channel(1);
+    tokio::task::spawn_local(run_key_reloader(
+        ctx.clone(),
+        Duration::from_secs(config.key_overlap),
+        load,
+        requests,
+    ));
+    tokio::task::spawn_local(reload_on_sighup(reloads.clone()));
+
+    if let Some(admin_listen) = config.admin_listen {
+        info!("Running with admin server");
+        let token = AdminToken(config.admin_token.clone());
+        tokio::task::spawn_local(async move {
+            if let Err(err) = run_admin_server(admin_listen, token, reloads).await {
+                error!("Admin server failed: {}", err);
+            }
+        });
+    }
 
-        let json: serde_json::Value = serde_json::from_str(&json_str).unwrap();
-        let n = json.get("n").unwrap().as_str().unwrap();
+    if !config.no_cron {
+        info!("Running with cron");
+        tokio::task::spawn_local(run_crons(ctx.clone()));
+    };
 
-        assert_eq!(encoded_n, n);
-    }
+    if !config.no_server {
+        info!("Running with server");
+        run_server(ctx.clone()).await.unwrap()
+    };
 }
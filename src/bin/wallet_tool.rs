@@ -1,16 +1,47 @@
 use std::{fs, io::stdin};
 
-use clap::{Parser, Subcommand};
+use clap::{Args as ClapArgs, Parser, Subcommand};
 use jsonwebkey::{JsonWebKey, Key, PublicExponent, RsaPrivate, RsaPublic};
 use log::debug;
 use openssl::rsa::Rsa;
 
-use validator::key_manager;
+use validator::{
+    key_manager,
+    keystore::{self, Keystore},
+};
+
+/// Where to take the password from, file takes precedence
+#[derive(ClapArgs)]
+struct PasswordOpts {
+    /// Path to file holding the password
+    #[clap(long)]
+    password_file: Option<String>,
+
+    /// Password, prefer using file or environment variable
+    #[clap(long, env = "WALLET_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+}
+
+impl PasswordOpts {
+    fn read(&self) -> Option<Vec<u8>> {
+        keystore::resolve_password(self.password_file.as_deref(), self.password.as_deref())
+            .expect("Failed to read password file")
+    }
+
+    fn require(&self) -> Vec<u8> {
+        self.read().expect("Password is required")
+    }
+}
 
 #[derive(Subcommand)]
 enum Command {
     /// Create new wallet
-    Create,
+    ///
+    /// Wallet is encrypted when a password is provided.
+    Create {
+        #[clap(flatten)]
+        password: PasswordOpts,
+    },
     /// Show Arweaver address for a wallet
     ShowAddress {
         /// Path to Arweave wallet file
@@ -19,6 +50,45 @@ enum Command {
         /// this application tries to read wallet data from stdin.
         #[clap(short = 'w', long)]
         wallet: Option<String>,
+
+        /// Password of encrypted wallet
+        #[clap(flatten)]
+        password: PasswordOpts,
+    },
+    /// Encrypt plaintext wallet into a keystore
+    Encrypt {
+        /// Path to Arweave wallet file, read from stdin when not provided
+        #[clap(short = 'w', long)]
+        wallet: Option<String>,
+
+        #[clap(flatten)]
+        password: PasswordOpts,
+    },
+    /// Decrypt keystore into plaintext wallet
+    Decrypt {
+        /// Path to keystore file, read from stdin when not provided
+        #[clap(short = 'k', long)]
+        keystore: Option<String>,
+
+        #[clap(flatten)]
+        password: PasswordOpts,
+    },
+    /// Re-encrypt keystore with a new password
+    ChangePassword {
+        /// Path to keystore file, read from stdin when not provided
+        #[clap(short = 'k', long)]
+        keystore: Option<String>,
+
+        #[clap(flatten)]
+        password: PasswordOpts,
+
+        /// Path to file holding the new password
+        #[clap(long)]
+        new_password_file: Option<String>,
+
+        /// New password, prefer using file or environment variable
+        #[clap(long, env = "WALLET_NEW_PASSWORD", hide_env_values = true)]
+        new_password: Option<String>,
     },
 }
 
@@ -28,11 +98,30 @@ struct Args {
     command: Command,
 }
 
+/// Read file contents or stdin when no path is given
+fn read_input(path: &Option<String>) -> String {
+    if let Some(path) = path {
+        fs::read_to_string(path).expect("Failed to find input file")
+    } else {
+        stdin().lines().fold(String::new(), |mut acc, line| {
+            acc.push_str(&line.expect("Failed to read a line"));
+            acc
+        })
+    }
+}
+
+fn print_keystore(keystore: &Keystore) {
+    println!(
+        "{}",
+        serde_json::to_string(keystore).expect("Failed to serialize keystore")
+    );
+}
+
 fn main() {
     let args = Args::parse();
 
     match args.command {
-        Command::Create => loop {
+        Command::Create { ref password } => loop {
             let rsa = Rsa::generate(4096)
                 .expect("Failed to generate enough random data for the private key");
             let jwk = JsonWebKey::new(Key::RSA {
@@ -52,26 +141,67 @@ fn main() {
 
             let address = key_manager::split_jwk(&jwk).2;
             if address.len() == 43 {
-                println!("{}", jwk);
+                match password.read() {
+                    Some(password) => print_keystore(
+                        &Keystore::encrypt(&jwk, &password).expect("Failed to encrypt wallet"),
+                    ),
+                    None => println!("{}", jwk),
+                }
                 break;
             }
         },
-        Command::ShowAddress { ref wallet } => {
+        Command::ShowAddress {
+            ref wallet,
+            ref password,
+        } => {
             let (_, _, address) = {
-                let wallet = if let Some(wallet) = wallet {
-                    fs::read_to_string(wallet).expect("Failed to find wallet file")
-                } else {
-                    let res = stdin().lines().fold(String::new(), |mut acc, line| {
-                        acc.push_str(&line.expect("Failed to read a line"));
-                        acc
-                    });
-                    res
-                };
-                let jwk: JsonWebKey = wallet.parse().expect("Failed to parse wallet file");
+                let wallet = read_input(wallet);
+                let jwk = keystore::parse_key(&wallet, password.read().as_deref())
+                    .expect("Failed to parse wallet file");
                 key_manager::split_jwk(&jwk)
             };
 
             println!(r#"{{"address":"{}"}}"#, address);
         }
+        Command::Encrypt {
+            ref wallet,
+            ref password,
+        } => {
+            let jwk: JsonWebKey = read_input(wallet)
+                .parse()
+                .expect("Failed to parse wallet file");
+            let keystore =
+                Keystore::encrypt(&jwk, &password.require()).expect("Failed to encrypt wallet");
+            print_keystore(&keystore);
+        }
+        Command::Decrypt {
+            keystore: ref keystore_file,
+            ref password,
+        } => {
+            let keystore: Keystore = serde_json::from_str(&read_input(keystore_file))
+                .expect("Failed to parse keystore file");
+            let jwk = keystore
+                .decrypt(&password.require())
+                .expect("Failed to decrypt keystore");
+            println!("{}", jwk);
+        }
+        Command::ChangePassword {
+            keystore: ref keystore_file,
+            ref password,
+            ref new_password_file,
+            ref new_password,
+        } => {
+            let keystore: Keystore = serde_json::from_str(&read_input(keystore_file))
+                .expect("Failed to parse keystore file");
+            let new_password =
+                keystore::resolve_password(new_password_file.as_deref(), new_password.as_deref())
+                    .expect("Failed to read new password file")
+                    .expect("New password is required");
+            debug!("Re-encrypting keystore");
+            let keystore = keystore
+                .change_password(&password.require(), &new_password)
+                .expect("Failed to change keystore password");
+            print_keystore(&keystore);
+        }
     }
 }
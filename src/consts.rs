@@ -1,2 +1,3 @@
 pub const VALIDATOR_AS_BUFFER: &[u8] = "Validator".as_bytes();
 pub const BUNDLR_AS_BUFFER: &[u8] = "Bundlr".as_bytes();
+pub const EPOCH_REPORT_AS_BUFFER: &[u8] = "EpochReport".as_bytes();
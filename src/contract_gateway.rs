@@ -28,7 +28,7 @@ struct VoteRequest<'a> {
 }
 
 #[derive(Clone, Debug, Deserialize)]
-struct VoteResponse {
+struct StatusResponse {
     status: String,
 }
 
@@ -74,27 +74,57 @@ impl ContractGateway {
         Context: http::ClientAccess<HttpClient>,
         HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
     {
-        let url = format!("{}validators/vote", self.url);
+        self.post(
+            ctx,
+            "validators/vote",
+            &VoteRequest {
+                tx: &proposal.id,
+                vote,
+            },
+        )
+        .await
+    }
+
+    /// Propose to slash the validator that cosigned the promise in `proposal`
+    pub async fn propose_slash<Context, HttpClient>(
+        &self,
+        ctx: &Context,
+        proposal: &SlashProposal,
+    ) -> Result<(), ContractGatewayError>
+    where
+        Context: http::ClientAccess<HttpClient>,
+        HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+    {
+        self.post(ctx, "validators/slash", proposal).await
+    }
+
+    async fn post<Context, HttpClient, Body>(
+        &self,
+        ctx: &Context,
+        path: &str,
+        body: &Body,
+    ) -> Result<(), ContractGatewayError>
+    where
+        Context: http::ClientAccess<HttpClient>,
+        HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+        Body: Serialize,
+    {
+        let url = format!("{}{}", self.url, path);
 
         let req = http::request::Builder::new()
             .method(Method::POST)
             .uri(url)
-            .body(
-                serde_json::to_string(&VoteRequest {
-                    tx: &proposal.id,
-                    vote,
-                })
-                .map_err(|err| {
-                    error!("Building request failed: {:?}", err);
-                    // TODO: needs better error
-                    ContractGatewayError::RequestFailed
-                })?,
-            )
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_string(body).map_err(|err| {
+                error!("Building request failed: {:?}", err);
+                // TODO: needs better error
+                ContractGatewayError::RequestFailed
+            })?)
             .map(|req| {
                 reqwest::Request::try_from(req)
                     .expect("Failed to convert http::request::Request into reqwest::Request")
             })
-            .expect("Failed to parse URL for fetching contract state");
+            .expect("Failed to parse URL of contract gateway");
 
         let res = ctx.get_http_client().execute(req).await.map_err(|err| {
             error!("Request failed: {:?}", err);
@@ -102,7 +132,7 @@ impl ContractGateway {
             ContractGatewayError::RequestFailed
         })?; // TODO: needs better error
 
-        let res: VoteResponse = res.json().await.map_err(|err| {
+        let res: StatusResponse = res.json().await.map_err(|err| {
             error!("Failed to deserialize the response: {:?}", err);
             // TODO: needs better error
             ContractGatewayError::RequestFailed
@@ -10,6 +10,8 @@ use std::str::FromStr;
 use url::Url;
 
 use crate::context::ArweaveAccess;
+use crate::database::queries::QueryContext;
+use crate::events::{EventsAccess, ValidatorEvent};
 use crate::http::Client;
 use crate::state::ValidatorStateAccess;
 
@@ -94,6 +96,40 @@ pub struct GraphqlQueryResponse {
     pub data: TransactionsGqlResponse,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BundledIn {
+    pub id: String,
+}
+
+/// Data item as known to the gateway
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct DataItem {
+    pub id: String,
+    #[serde(rename = "bundledIn")]
+    pub bundled_in: Option<BundledIn>,
+    pub block: Option<BlockInfo>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DataItemNode {
+    pub node: DataItem,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DataItemEdges {
+    pub edges: Vec<DataItemNode>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DataItemsGqlResponse {
+    pub transactions: DataItemEdges,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DataItemQueryResponse {
+    pub data: DataItemsGqlResponse,
+}
+
 #[derive(Deserialize, Serialize, Default, Clone)]
 pub struct TransactionStatus {
     pub block_indep_hash: String,
@@ -102,7 +138,7 @@ pub struct TransactionStatus {
 use derive_more::{Display, Error};
 use std::convert::From;
 
-use super::CronJobError;
+use super::{save_state_if_changed, CronJobError};
 
 #[derive(Debug, Display, Error, Clone, PartialEq)]
 pub enum ArweaveError {
@@ -138,9 +174,14 @@ pub struct GqlVariables {
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-pub struct ReqBody {
+pub struct DataItemVariables {
+    pub ids: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ReqBody<Variables = GqlVariables> {
     pub query: String,
-    pub variables: GqlVariables,
+    pub variables: Variables,
 }
 
 pub trait ArweaveContext<HttpClient>
@@ -227,67 +268,126 @@ impl Arweave {
         }
     }
 
+    /// Page of transactions signed by `owner`, oldest first, starting after
+    /// the transaction `after` is the cursor of
+    ///
+    /// Pending transactions come last, their position changes once they are
+    /// mined. Returns the transactions with their cursors and whether there
+    /// are more pages.
     pub async fn get_latest_transactions<Context, HttpClient>(
         &self,
         ctx: &Context,
         owner: &str,
         first: Option<i64>,
         after: Option<String>,
-    ) -> Result<(Vec<Transaction>, bool, Option<String>), ArweaveError>
+    ) -> Result<(Vec<GraphqlNodes>, bool), ArweaveError>
     where
         Context: ArweaveContext<HttpClient>,
         HttpClient: Client<Request = reqwest::Request, Response = reqwest::Response>,
     {
-        let raw_query = "query($owners: [String!], $first: Int) { transactions(owners: $owners, first: $first) { pageInfo { hasNextPage } edges { cursor node { id owner { address } signature recipient tags { name value } block { height id timestamp } } } } }";
-        let raw_variables = format!(
-            "{{\"owners\": [\"{}\"], \"first\": {}, \"after\": {}}}",
-            owner,
-            first.unwrap_or(10),
-            match after {
-                None => r"null".to_string(),
-                Some(a) => a,
-            }
-        );
+        let raw_query = "query($owners: [String!], $first: Int, $after: String) { transactions(owners: $owners, first: $first, after: $after, sort: HEIGHT_ASC) { pageInfo { hasNextPage } edges { cursor node { id owner { address } signature recipient tags { name value } block { height id timestamp } } } } }";
 
         let url = self
             .get_host()
             .join(&format!("graphql?query={}", urlencoding::encode(raw_query)))
             .expect("Invalid URL"); // FIXME: change result to support failing here
 
-        // TODO: why to build object by parsing from string and then turn it later back to string
-        let body = format!(
-            "{{\"query\":\"{}\",\"variables\":{}}}",
-            raw_query, raw_variables
-        );
+        let body = ReqBody {
+            query: raw_query.to_string(),
+            variables: GqlVariables {
+                owners: vec![owner.to_string()],
+                first: first.unwrap_or(10) as u128,
+                after,
+            },
+        };
+        let body = serde_json::to_string(&body).expect("Failed to serialize GraphQL query");
 
         let req: http::Request<String> = http::request::Builder::new()
             .method(http::Method::POST)
             .uri(url.to_string())
+            .header("Content-Type", "application/json")
             .body(body)
             .expect("Failed to create request for fetching latest transactions");
 
         let req: reqwest::Request = reqwest::Request::try_from(req).unwrap();
 
-        let res = ctx.get_client().execute(req).await.unwrap(); // FIXME: do not unwrap
+        let res = ctx.get_client().execute(req).await.map_err(|err| {
+            error!("Request for transactions of {} failed: {:?}", owner, err);
+            ArweaveError::UnknownErr
+        })?;
 
         match res.status() {
             reqwest::StatusCode::OK => {
-                let res: GraphqlQueryResponse = res.json().await.unwrap(); // FIXME: do not unwrap
-                let mut txs: Vec<Transaction> = Vec::<Transaction>::new();
-                let mut end_cursor: Option<String> = None;
-                for tx in &res.data.transactions.edges {
-                    txs.push(tx.node.clone());
-                    end_cursor = Some(tx.cursor.clone());
-                }
-                let has_next_page = res.data.transactions.page_info.has_next_page;
+                let res: GraphqlQueryResponse = res.json().await.map_err(|err| {
+                    error!("Failed to deserialize transactions of {}: {:?}", owner, err);
+                    ArweaveError::UnknownErr
+                })?;
+                let transactions = res.data.transactions;
+
+                Ok((transactions.edges, transactions.page_info.has_next_page))
+            }
+            status => Err(status_error(status)),
+        }
+    }
+
+    /// Bundle and block a data item was included in
+    ///
+    /// Returns `None` if the gateway doesn't know the data item. Bundle and
+    /// block are missing while the data item, or its bundle, is pending.
+    pub async fn get_data_item<Context, HttpClient>(
+        &self,
+        ctx: &Context,
+        id: &str,
+    ) -> Result<Option<DataItem>, ArweaveError>
+    where
+        Context: ArweaveContext<HttpClient>,
+        HttpClient: Client<Request = reqwest::Request, Response = reqwest::Response>,
+    {
+        let raw_query = "query($ids: [ID!]) { transactions(ids: $ids) { edges { node { id bundledIn { id } block { height id timestamp } } } } }";
+
+        let url = self
+            .get_host()
+            .join(&format!("graphql?query={}", urlencoding::encode(raw_query)))
+            .expect("Invalid URL"); // FIXME: change result to support failing here
+
+        let body = ReqBody {
+            query: raw_query.to_string(),
+            variables: DataItemVariables {
+                ids: vec![id.to_string()],
+            },
+        };
+        let body = serde_json::to_string(&body).expect("Failed to serialize GraphQL query");
+
+        let req: http::Request<String> = http::request::Builder::new()
+            .method(http::Method::POST)
+            .uri(url.to_string())
+            .header("Content-Type", "application/json")
+            .body(body)
+            .expect("Failed to create request for fetching data item");
+
+        let req: reqwest::Request = reqwest::Request::try_from(req).unwrap();
+
+        let res = ctx.get_client().execute(req).await.map_err(|err| {
+            error!("Request for data item {} failed: {:?}", id, err);
+            ArweaveError::UnknownErr
+        })?;
 
-                Ok((txs, has_next_page, end_cursor))
+        match res.status() {
+            reqwest::StatusCode::OK => {
+                let res: DataItemQueryResponse = res.json().await.map_err(|err| {
+                    error!("Failed to deserialize data item {}: {:?}", id, err);
+                    ArweaveError::UnknownErr
+                })?;
+
+                Ok(res
+                    .data
+                    .transactions
+                    .edges
+                    .into_iter()
+                    .map(|edge| edge.node)
+                    .find(|item| item.id == id))
             }
-            reqwest::StatusCode::BAD_REQUEST => Err(ArweaveError::MalformedQuery),
-            reqwest::StatusCode::NOT_FOUND => Err(ArweaveError::TxsNotFound),
-            reqwest::StatusCode::INTERNAL_SERVER_ERROR => Err(ArweaveError::InternalServerError),
-            reqwest::StatusCode::GATEWAY_TIMEOUT => Err(ArweaveError::GatewayTimeout),
-            _ => Err(ArweaveError::UnknownErr),
+            status => Err(status_error(status)),
         }
     }
 
@@ -296,9 +396,23 @@ impl Arweave {
     }
 }
 
+fn status_error(status: reqwest::StatusCode) -> ArweaveError {
+    match status {
+        reqwest::StatusCode::BAD_REQUEST => ArweaveError::MalformedQuery,
+        reqwest::StatusCode::NOT_FOUND => ArweaveError::TxsNotFound,
+        reqwest::StatusCode::INTERNAL_SERVER_ERROR => ArweaveError::InternalServerError,
+        reqwest::StatusCode::GATEWAY_TIMEOUT => ArweaveError::GatewayTimeout,
+        _ => ArweaveError::UnknownErr,
+    }
+}
+
 pub async fn sync_network_info<Context, HttpClient>(ctx: &Context) -> Result<(), CronJobError>
 where
-    Context: ArweaveContext<HttpClient> + ArweaveAccess + ValidatorStateAccess,
+    Context: ArweaveContext<HttpClient>
+        + ArweaveAccess
+        + EventsAccess
+        + QueryContext
+        + ValidatorStateAccess,
     HttpClient: crate::http::Client<Request = reqwest::Request, Response = reqwest::Response>,
     HttpClient::Error: From<reqwest::Error>,
 {
@@ -308,10 +422,19 @@ where
     })?;
 
     let state = ctx.get_validator_state();
+    let before = state.snapshot();
 
     info!("Update state: current_block={}", network_info.height);
     state.set_current_block(network_info.height);
 
+    save_state_if_changed(ctx, &before)?;
+    if network_info.height != before.current_block {
+        ctx.events().publish(ValidatorEvent::NewBlock {
+            height: network_info.height,
+        });
+    }
+    state.mark_network_synced();
+
     Ok(())
 }
 
@@ -436,7 +559,7 @@ mod tests {
     async fn get_latest_transactions_should_return_ok() {
         let client = MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
             .when(|req: &Request| {
-                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
+                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%2C%20%24after%3A%20String%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%2C%20after%3A%20%24after%2C%20sort%3A%20HEIGHT_ASC%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
                 req.method() == Method::POST && &req.url().to_string() == url
             })
             .then(|_: &Request| {
@@ -461,11 +584,96 @@ mod tests {
             .unwrap();
     }
 
+    #[actix_rt::test]
+    async fn get_latest_transactions_continues_after_cursor() {
+        let client = MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
+            .when(|req: &Request| {
+                let body: serde_json::Value = req
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .and_then(|body| serde_json::from_slice(body).ok())
+                    .unwrap_or_default();
+                req.method() == Method::POST
+                    && body["variables"]["after"] == "cursor_1"
+                    && body["variables"]["first"] == 50
+            })
+            .then(|_: &Request| {
+                let data = "{\"data\": {\"transactions\": {\"pageInfo\": {\"hasNextPage\": false },\"edges\": [{\"cursor\": \"cursor_2\", \"node\": { \"id\": \"tx_id\",\"owner\": {\"address\": \"address\"}, \"signature\": \"signature\",\"recipient\": \"\", \"tags\": [], \"block\": { \"id\": \"id\", \"timestamp\": 10, \"height\": 10 } } } ] } } }";
+                let response = http::response::Builder::new()
+                    .status(200)
+                    .body(data)
+                    .unwrap();
+                Response::from(response)
+            });
+
+        let (key_manager, _bundle_pvk) = test_keys();
+        let ctx = test_context_with_http_client(key_manager, client);
+        let arweave = Arweave {
+            url: Url::from_str("http://example.com").unwrap(),
+        };
+        let (edges, has_next_page) = arweave
+            .get_latest_transactions(&ctx, "owner", Some(50), Some("cursor_1".to_string()))
+            .await
+            .unwrap();
+
+        assert!(!has_next_page);
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].cursor, "cursor_2");
+        assert_eq!(edges[0].node.block.as_ref().unwrap().height, 10);
+    }
+
+    #[actix_rt::test]
+    async fn get_data_item_returns_bundle_and_block() {
+        let client = MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
+            .when(|req: &Request| {
+                let body: serde_json::Value = req
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .and_then(|body| serde_json::from_slice(body).ok())
+                    .unwrap_or_default();
+                req.method() == Method::POST && body["variables"]["ids"][0] == "data_item"
+            })
+            .then(|_: &Request| {
+                let data = "{\"data\": {\"transactions\": {\"edges\": [{\"node\": { \"id\": \"data_item\", \"bundledIn\": { \"id\": \"bundle\" }, \"block\": { \"id\": \"id\", \"timestamp\": 10, \"height\": 10 } } } ] } } }";
+                let response = http::response::Builder::new()
+                    .status(200)
+                    .body(data)
+                    .unwrap();
+                Response::from(response)
+            })
+            .when(|req: &Request| req.method() == Method::POST)
+            .then(|_: &Request| {
+                let data = "{\"data\": {\"transactions\": {\"edges\": [] } } }";
+                let response = http::response::Builder::new()
+                    .status(200)
+                    .body(data)
+                    .unwrap();
+                Response::from(response)
+            });
+
+        let (key_manager, _bundle_pvk) = test_keys();
+        let ctx = test_context_with_http_client(key_manager, client);
+        let arweave = Arweave {
+            url: Url::from_str("http://example.com").unwrap(),
+        };
+
+        let item = arweave
+            .get_data_item(&ctx, "data_item")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.bundled_in.unwrap().id, "bundle");
+        assert_eq!(item.block.unwrap().height, 10);
+
+        let item = arweave.get_data_item(&ctx, "unknown").await.unwrap();
+        assert!(item.is_none());
+    }
+
     #[actix_rt::test]
     async fn gateway_address_with_slash_in_the_end() {
         let client = MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
             .when(|req: &Request| {
-                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
+                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%2C%20%24after%3A%20String%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%2C%20after%3A%20%24after%2C%20sort%3A%20HEIGHT_ASC%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
                 req.method() == Method::POST && &req.url().to_string() == url
             })
             .then(|_: &Request| {
@@ -494,7 +702,7 @@ mod tests {
     async fn gateway_address_without_slash_in_the_end() {
         let client = MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
             .when(|req: &Request| {
-                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
+                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%2C%20%24after%3A%20String%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%2C%20after%3A%20%24after%2C%20sort%3A%20HEIGHT_ASC%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
                 req.method() == Method::POST && &req.url().to_string() == url
             })
             .then(|_: &Request| {
@@ -2,14 +2,11 @@ extern crate diesel;
 
 use super::arweave::{self, ArweaveContext};
 use super::error::ValidatorCronError;
-use super::slasher::vote_slash;
-use super::transactions::get_transactions;
-use crate::bundler::Bundler;
 use crate::context::{ArweaveAccess, BundlerAccess};
 use crate::cron::arweave::{Arweave, Transaction as ArweaveTx};
 use crate::database::models::{Block, Epoch, NewBundle, NewTransaction};
 use crate::database::queries::{self, *};
-use crate::http::{self, Client};
+use crate::http;
 use crate::key_manager;
 use crate::key_manager::KeyManagerAccess;
 use crate::types::Validator;
@@ -27,6 +24,17 @@ pub struct TxReceipt {
     signature: String,
 }
 
+/// Bundles requested from the gateway at once
+const BUNDLES_PAGE_SIZE: i64 = 50;
+/// Pages validated in one run, the next run continues from the checkpoint
+const PAGES_PER_RUN: usize = 20;
+
+/// Walk the bundler's bundles, oldest first, and validate their transactions
+///
+/// Cursor of every validated bundle is stored, so that validation resumes
+/// after the last validated bundle following a restart. Validation stops at
+/// the first pending bundle and at bundles that couldn't be checked, those
+/// are tried again on the next run.
 pub async fn validate_bundler<Context, HttpClient, KeyManager>(
     ctx: &Context,
 ) -> Result<(), ValidatorCronError>
@@ -42,32 +50,63 @@ where
 {
     let arweave = ctx.arweave();
     let bundler = ctx.bundler();
-    let latest_transactions_response = arweave
-        .get_latest_transactions(ctx, &bundler.address, Some(50), None)
-        .await;
 
-    let latest_transactions = match latest_transactions_response {
-        Err(err) => {
-            error!(
-                "Error occurred while getting txs from bundler address: \n {}. Error: {}",
-                bundler.address, err
-            );
-            return Err(ValidatorCronError::TxsFromAddressNotFound);
+    let checkpoint = get_validation_checkpoint(ctx).map_err(|err| {
+        error!("Failed to load bundle validation checkpoint: {}", err);
+        ValidatorCronError::CheckpointNotLoaded
+    })?;
+    let mut after = checkpoint.map(|checkpoint| {
+        info!(
+            "Resuming bundle validation after block {}",
+            checkpoint.block.0
+        );
+        checkpoint.cursor
+    });
+
+    for _ in 0..PAGES_PER_RUN {
+        let (bundles, has_next_page) = arweave
+            .get_latest_transactions(
+                ctx,
+                &bundler.address,
+                Some(BUNDLES_PAGE_SIZE),
+                after.clone(),
+            )
+            .await
+            .map_err(|err| {
+                error!(
+                    "Error occurred while getting txs from bundler address: \n {}. Error: {}",
+                    bundler.address, err
+                );
+                ValidatorCronError::TxsFromAddressNotFound
+            })?;
+        let last_page = !has_next_page || bundles.is_empty();
+
+        for edge in bundles {
+            let bundle = &edge.node;
+            let block = match check_bundle_block(bundle)? {
+                Some(block) => block,
+                // Pending bundles come last and move once mined
+                None => return Ok(()),
+            };
+
+            // Checkpoint stays in front of bundles that couldn't be checked
+            match validate_bundle(ctx, arweave, bundle, block).await {
+                Ok(()) => (),
+                Err(err) => {
+                    error!("Validation of bundle {} failed: {}", &bundle.id, err);
+                    return Err(err);
+                }
+            }
+
+            save_validation_checkpoint(ctx, &edge.cursor, block).map_err(|err| {
+                error!("Failed to save bundle validation checkpoint: {}", err);
+                ValidatorCronError::CheckpointNotSaved
+            })?;
+            after = Some(edge.cursor);
         }
-        Ok((latest_transactions, _, _)) => latest_transactions,
-    };
 
-    for bundle in latest_transactions {
-        let res = validate_bundle(ctx, arweave, &bundle).await;
-        if let Err(err) = res {
-            match err {
-                ValidatorCronError::TxNotFound => todo!(),
-                ValidatorCronError::AddressNotFound => todo!(),
-                ValidatorCronError::TxsFromAddressNotFound => todo!(),
-                ValidatorCronError::BundleNotInsertedInDB => todo!(),
-                ValidatorCronError::TxInvalid => todo!(),
-                ValidatorCronError::FileError => (),
-            }
+        if last_page {
+            break;
         }
     }
 
@@ -78,6 +117,7 @@ async fn validate_bundle<Context, HttpClient, KeyManager>(
     ctx: &Context,
     arweave: &Arweave,
     bundle: &ArweaveTx,
+    current_block: u128,
 ) -> Result<(), ValidatorCronError>
 where
     Context: queries::QueryContext
@@ -88,13 +128,6 @@ where
     HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
     KeyManager: key_manager::KeyManager,
 {
-    let block_ok = check_bundle_block(bundle);
-    let current_block = match block_ok {
-        Err(err) => return Err(err),
-        Ok(None) => return Ok(()),
-        Ok(Some(block)) => block,
-    };
-
     store_bundle(ctx, bundle, current_block)?;
 
     let path = match arweave.get_tx_data(ctx, &bundle.id).await {
@@ -107,8 +140,8 @@ where
 
     let bundle_txs = match verify_file_bundle(path.clone()).await {
         Err(r) => {
-            error!("Error verifying bundle {}:", r);
-            Vec::new()
+            error!("Error verifying bundle {}: {}", &bundle.id, r);
+            return Err(ValidatorCronError::BundleNotVerified);
         }
         Ok(v) => v,
     };
@@ -119,10 +152,10 @@ where
         &bundle.id
     );
     for bundle_tx in bundle_txs {
-        let tx_receipt = verify_bundle_tx(ctx, &bundle_tx, Some(current_block)).await;
+        let tx_receipt = verify_bundle_tx(ctx, &bundle_tx, current_block).await;
         if let Err(err) = tx_receipt {
-            info!("Error found in transaction {} : {}", &bundle_tx.tx_id, err);
-            return Err(ValidatorCronError::TxInvalid);
+            error!("Error checking transaction {} : {}", &bundle_tx.tx_id, err);
+            return Err(err);
         }
     }
     info!("All transactions ok in bundle {}", &bundle.id);
@@ -185,7 +218,7 @@ where
 async fn verify_bundle_tx<Context, HttpClient, KeyManager>(
     ctx: &Context,
     bundle_tx: &Item,
-    current_block: Option<u128>,
+    current_block: u128,
 ) -> Result<(), ValidatorCronError>
 where
     Context: queries::QueryContext + KeyManagerAccess<KeyManager> + http::ClientAccess<HttpClient>,
@@ -202,7 +235,10 @@ where
             tx_id: tx.id,
             signature: match std::str::from_utf8(&tx.signature.to_vec()) {
                 Ok(v) => v.to_string(),
-                Err(e) => panic!("Invalid UTF-8 seq: {}", e),
+                Err(e) => {
+                    error!("Stored receipt for {} is not valid UTF-8: {}", &bundle_tx.tx_id, e);
+                    return Err(ValidatorCronError::ReceiptNotVerified);
+                }
             },
         });
     } else {
@@ -214,17 +250,24 @@ where
 
     match tx_receipt {
         Some(receipt) => {
-            let tx_is_ok = verify_tx_receipt(ctx.get_key_manager(), &receipt).unwrap();
-            // FIXME: don't use unwrap
-            if tx_is_ok && receipt.block <= current_block.unwrap() {
+            let tx_is_ok =
+                verify_tx_receipt(&ctx.get_key_manager(), &receipt).map_err(|err| {
+                    error!("Failed to verify receipt for {}: {}", &receipt.tx_id, err);
+                    ValidatorCronError::ReceiptNotVerified
+                })?;
+            if tx_is_ok && receipt.block <= current_block {
                 let tx = NewTransaction {
                     id: receipt.tx_id,
                     epoch: Epoch(0),
                     block_promised: receipt.block.into(),
-                    block_actual: current_block.map(Block),
+                    block_actual: Some(Block(current_block)),
                     signature: receipt.signature.as_bytes().to_vec(),
                     validated: true,
                     bundle_id: Some(bundle_tx.tx_id.clone()),
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
                 };
                 if let Err(err) = insert_tx_in_db(ctx, &tx) {
                     error!("Error inserting new tx {}, Error: {}", tx.id, err);
@@ -297,45 +340,22 @@ where
         DeepHashChunk::Chunk(tx_id.into()),
         DeepHashChunk::Chunk(block.into()),
     ]))
-    .unwrap();
+    .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", err)))?;
 
     let sig = BASE64URL_NOPAD
         .decode(tx_receipt.signature.as_bytes())
-        .unwrap();
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
 
     Ok(key_manager.verify_bundler_signature(&message, &sig))
 }
 
-pub async fn validate_transactions<HttpClient>(
-    http_client: &HttpClient,
-    bundler: &Bundler,
-) -> Result<(), ValidatorCronError>
-where
-    HttpClient: Client<Request = reqwest::Request, Response = reqwest::Response>,
-{
-    let res = get_transactions(http_client, bundler, Some(100), None).await;
-    let txs = match res {
-        Ok(r) => r.0,
-        Err(_) => Vec::new(),
-    };
-
-    for tx in txs {
-        // TODO: validate transacitons
-        let block_ok = tx.current_block < tx.expected_block;
-
-        if block_ok {
-            let _res = vote_slash(bundler);
-        }
-    }
-
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use crate::utils::get_file_as_byte_vector;
     use crate::{
-        context::test_utils::test_context_with_http_client, http::reqwest::mock::MockHttpClient,
+        context::test_utils::test_context_with_http_client,
+        database::{models::Block, queries::get_validation_checkpoint},
+        http::reqwest::mock::MockHttpClient,
         key_manager::test_utils::test_keys,
     };
     use http::Method;
@@ -347,7 +367,7 @@ mod tests {
     async fn validate_bundler_should_abort_due_no_block() {
         let client = MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
             .when(|req: &Request| {
-                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
+                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%2C%20%24after%3A%20String%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%2C%20after%3A%20%24after%2C%20sort%3A%20HEIGHT_ASC%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
                 req.method() == Method::POST && &req.url().to_string() == url
             })
             .then(|_: &Request| {
@@ -381,11 +401,11 @@ mod tests {
     async fn validate_bundler_should_return_ok() {
         let client = MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
             .when(|req: &Request| {
-                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
+                let url = "http://example.com/graphql?query=query%28%24owners%3A%20%5BString%21%5D%2C%20%24first%3A%20Int%2C%20%24after%3A%20String%29%20%7B%20transactions%28owners%3A%20%24owners%2C%20first%3A%20%24first%2C%20after%3A%20%24after%2C%20sort%3A%20HEIGHT_ASC%29%20%7B%20pageInfo%20%7B%20hasNextPage%20%7D%20edges%20%7B%20cursor%20node%20%7B%20id%20owner%20%7B%20address%20%7D%20signature%20recipient%20tags%20%7B%20name%20value%20%7D%20block%20%7B%20height%20id%20timestamp%20%7D%20%7D%20%7D%20%7D%20%7D";
                 req.method() == Method::POST && &req.url().to_string() == url
             })
             .then(|_: &Request| {
-                let data = "{\"data\": {\"transactions\": {\"pageInfo\": {\"hasNextPage\": true },\"edges\": [{\"cursor\": \"cursor\", \"node\": { \"id\": \"tx_id\",\"owner\": {\"address\": \"address\"}, \"signature\": \"signature\", \"recipient\": \"\", \"tags\": [], \"block\": { \"id\": \"id\", \"timestamp\": 10, \"height\": 10 } } } ] } } }";
+                let data = "{\"data\": {\"transactions\": {\"pageInfo\": {\"hasNextPage\": false },\"edges\": [{\"cursor\": \"cursor\", \"node\": { \"id\": \"tx_id\",\"owner\": {\"address\": \"address\"}, \"signature\": \"signature\", \"recipient\": \"\", \"tags\": [], \"block\": { \"id\": \"id\", \"timestamp\": 10, \"height\": 10 } } } ] } } }";
                 let response = http::response::Builder::new()
                     .status(200)
                     .body(data)
@@ -408,6 +428,11 @@ mod tests {
         let (key_manager, _bundle_pvk) = test_keys();
         let ctx = test_context_with_http_client(key_manager, client);
         let res = validate_bundler(&ctx).await;
-        assert!(res.is_ok())
+        assert!(res.is_ok());
+
+        // Next run resumes after the validated bundle
+        let checkpoint = get_validation_checkpoint(&ctx).unwrap().unwrap();
+        assert_eq!(checkpoint.cursor, "cursor");
+        assert_eq!(checkpoint.block, Block(10));
     }
 }
@@ -1,3 +1,5 @@
+use log::info;
+
 use crate::{database::{queries::{QueryContext, filter}}};
 
 use super::CronJobError;
@@ -9,6 +11,6 @@ where
 {
   let epoch = ctx.current_epoch();
   filter(ctx, epoch, 40).await
-    .map(|amount| print!( "Deleted {} transactions from epoch {} to {}", amount, epoch - 40, epoch))
+    .map(|amount| info!("Deleted {} transactions from epoch {} to {}", amount, epoch.saturating_sub(40), epoch))
     .map_err(|err| CronJobError::ValidatorError(ValidatorCronError::from(err)))
 }
\ No newline at end of file
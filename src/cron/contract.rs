@@ -1,11 +1,18 @@
 use std::str::FromStr;
 
+use log::{error, info, warn};
+
 use crate::{
+    bundler_keys::bundler_keys_at,
     context, contract_gateway,
-    state::{self, ValidatorRole},
+    database::queries,
+    events::{EventsAccess, ValidatorEvent},
+    leader,
+    server::routes::sign::SignRequest,
+    state::{self, ScheduledEpoch, ValidatorRole},
 };
 
-use super::{arweave::ArweaveError, http, CronJobError};
+use super::{arweave, error::ValidatorCronError, http, save_state_if_changed, CronJobError};
 
 use bundlr_contracts_validators::{
     slashing::Proposal as SlashProposal,
@@ -15,10 +22,13 @@ use bundlr_contracts_validators::{
 
 pub async fn check_contract_updates<Context, HttpClient>(ctx: &Context) -> Result<(), CronJobError>
 where
-    Context: context::ArweaveAccess
+    Context: arweave::ArweaveContext<HttpClient>
+        + context::ArweaveAccess
         + context::ValidatorAddressAccess
         + contract_gateway::ContractGatewayAccess
+        + EventsAccess
         + http::ClientAccess<HttpClient>
+        + queries::QueryContext
         + state::ValidatorStateAccess,
     HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
 {
@@ -29,19 +39,51 @@ where
         .await
         .map_err(CronJobError::ContractGatewayError)?;
 
+    let before = ctx.get_validator_state().snapshot();
+
+    // Record the epoch announced by the contract even before it activates, so
+    // that requests promising blocks in the next epoch can be resolved
+    ctx.get_validator_state().schedule_epoch(ScheduledEpoch {
+        seq: state.epoch.seq,
+        height: state.epoch.height,
+        role: role_in_epoch(ctx, &state),
+    });
+
     if let Some((new_epoch, new_role)) = check_for_epoch_update(ctx, &state).await {
         let state = ctx.get_validator_state();
+        let previous_role = state.role();
         state.set_current_epoch(new_epoch.seq);
         state.set_role(new_role);
+
+        ctx.events().publish(ValidatorEvent::EpochChanged {
+            seq: new_epoch.seq,
+            height: new_epoch.height,
+        });
+        if previous_role != new_role {
+            ctx.events().publish(ValidatorEvent::RoleChanged {
+                epoch: new_epoch.seq,
+                previous: previous_role,
+                role: new_role,
+            });
+        }
+    }
+
+    save_state_if_changed(ctx, &before)?;
+    if ctx.get_validator_state().current_epoch() == state.epoch.seq {
+        record_leader(ctx, &state)?;
     }
+    ctx.get_validator_state().mark_contract_synced();
 
     if let Some(new_slash_proposals) = check_for_slash_proposals(ctx, &state).await {
         for proposal in new_slash_proposals {
-            let is_valid = is_valid_proposal(ctx, proposal)
-                .await
-                .map_err(CronJobError::ArweaveError)?;
+            ctx.events()
+                .publish(ValidatorEvent::NewSlashProposal(proposal.clone()));
 
-            let vote = if is_valid { Vote::For } else { Vote::Against };
+            // Proposals that can't be judged yet are looked at again on the next run
+            let vote = match proposal_vote::<_, HttpClient>(ctx, proposal).await {
+                Some(vote) => vote,
+                None => continue,
+            };
             contract_gateway
                 .vote_for_proposal(ctx, proposal, vote)
                 .await
@@ -65,18 +107,50 @@ where
     };
 
     if state.epoch.seq > current_epoch && state.epoch.height <= current_block_height {
-        let validator_address = Address::from_str(ctx.get_validator_address()).unwrap();
-        let role = if state.nominated_validators.contains(&validator_address) {
-            ValidatorRole::Cosigner
-        } else {
-            ValidatorRole::Idle
-        };
-        Some((&state.epoch, role))
+        Some((&state.epoch, role_in_epoch(ctx, state)))
     } else {
         None
     }
 }
 
+fn role_in_epoch<Context>(ctx: &Context, state: &ContractState) -> ValidatorRole
+where
+    Context: context::ValidatorAddressAccess,
+{
+    let validator_address = Address::from_str(&ctx.get_validator_address()).unwrap();
+    if leader::epoch_leader(state) == Some(&validator_address) {
+        ValidatorRole::Leader
+    } else if state.nominated_validators.contains(&validator_address) {
+        ValidatorRole::Cosigner
+    } else {
+        ValidatorRole::Idle
+    }
+}
+
+/// Store known validators and the leader of the active epoch, cosigners
+/// forward their receipts to it
+fn record_leader<Context>(ctx: &Context, state: &ContractState) -> Result<(), CronJobError>
+where
+    Context: queries::QueryContext,
+{
+    let known: Vec<(String, Option<String>)> = state
+        .validators
+        .values()
+        .map(|validator| {
+            (
+                validator.address.to_string(),
+                Some(validator.url.to_string()),
+            )
+        })
+        .collect();
+    let leader = leader::epoch_leader(state).map(|address| address.to_string());
+
+    leader::record_leader(&ctx.get_db_connection(), &known, leader.as_deref()).map_err(|err| {
+        error!("Failed to record epoch leader: {}", err);
+        CronJobError::ValidatorError(ValidatorCronError::LeaderNotRecorded)
+    })
+}
+
 async fn check_for_slash_proposals<'a, Context>(
     ctx: &Context,
     state: &'a ContractState,
@@ -84,7 +158,7 @@ async fn check_for_slash_proposals<'a, Context>(
 where
     Context: context::ValidatorAddressAccess,
 {
-    let own_address = Address::from_str(ctx.get_validator_address()).unwrap();
+    let own_address = Address::from_str(&ctx.get_validator_address()).unwrap();
     let new_proposals: Vec<&SlashProposal> = state
         .slash_proposals
         .iter()
@@ -111,26 +185,109 @@ where
     }
 }
 
-async fn is_valid_proposal<Context>(
-    _ctx: &Context,
-    _proposal: &SlashProposal,
-) -> Result<bool, ArweaveError> {
-    // TODO: implement actual logic for checking if the proposal is valid
-    Ok(true)
+/// Vote for `proposal` if its promise was signed by the bundler and the data
+/// item wasn't included in a bundle by the promised block
+///
+/// The promise is checked against the bundler keys in use around the
+/// promised block. `None` if that can't be told yet, e.g. while the gateway
+/// doesn't know the data item.
+async fn proposal_vote<Context, HttpClient>(ctx: &Context, proposal: &SlashProposal) -> Option<Vote>
+where
+    Context: arweave::ArweaveContext<HttpClient>
+        + context::ArweaveAccess
+        + queries::QueryContext
+        + state::ValidatorStateAccess,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+{
+    let promise = SignRequest::from(proposal);
+
+    let keys = match bundler_keys_at(&ctx.get_db_connection(), promise.block()) {
+        Ok(keys) => keys,
+        Err(err) => {
+            error!(
+                "Failed to load bundler keys for slash proposal for {}: {}",
+                promise.id(),
+                err
+            );
+            return None;
+        }
+    };
+    match promise.verify_with_keys(&keys).await {
+        Ok(true) => (),
+        _ => {
+            warn!(
+                "Slash proposal for {} doesn't carry a valid bundler signature",
+                promise.id()
+            );
+            return Some(Vote::Against);
+        }
+    }
+
+    // Data item may still be included in time
+    if ctx.get_validator_state().current_block() <= promise.block() {
+        warn!(
+            "Slash proposal for {} made before promised block {}",
+            promise.id(),
+            promise.block()
+        );
+        return Some(Vote::Against);
+    }
+
+    let item = match ctx.arweave().get_data_item(ctx, promise.id()).await {
+        Ok(Some(item)) => item,
+        Ok(None) => {
+            info!(
+                "Data item {} of slash proposal is not known to the gateway yet",
+                promise.id()
+            );
+            return None;
+        }
+        Err(err) => {
+            error!(
+                "Failed to look up data item {} of slash proposal: {}",
+                promise.id(),
+                err
+            );
+            return None;
+        }
+    };
+
+    match item.block.map(|block| block.height) {
+        Some(height) if height <= promise.block() => {
+            info!(
+                "Data item {} of slash proposal was included at block {}, promised {}",
+                promise.id(),
+                height,
+                promise.block()
+            );
+            Some(Vote::Against)
+        }
+        _ => Some(Vote::For),
+    }
 }
 
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
 
+    use diesel::RunQueryDsl;
+
     use crate::{
         context::test_utils::test_context_with_http_client,
+        database::{
+            models::{Block, NewBundlerKey},
+            queries::QueryContext,
+            schema::bundler_key_history,
+        },
         http::reqwest::mock::MockHttpClient,
         key_manager::{
+            address_from_modulus,
             test_utils::{test_keys, to_address, validator_key},
-            KeyManager,
+            InMemoryKeyManager, KeyManager,
         },
-        state::ValidatorStateAccess,
+        leader::epoch_leader,
+        server::routes::sign::test_utils::test_message,
+        state::{ValidatorRole, ValidatorStateAccess},
     };
     use bundlr_contracts_validators::{
         slashing::{Proposal, Vote, Voting},
@@ -138,6 +295,7 @@ mod test {
     };
     use futures::executor::LocalPool;
     use http::Method;
+    use openssl::pkey::{PKey, Private};
     use reqwest;
 
     use super::check_contract_updates;
@@ -202,14 +360,42 @@ mod test {
         rt.run_until(check_contract_updates(&ctx)).unwrap();
 
         assert_eq!(ctx.get_validator_state().current_epoch(), 0);
+        // Upcoming epoch is known before it activates, the only nominated
+        // validator leads it
+        assert_eq!(
+            ctx.get_validator_state().role_at(1),
+            Some(ValidatorRole::Leader)
+        );
     }
 
     #[test]
-    fn epoch_update_at_the_activation_block_height_yields_epoch_being_updated() {
+    fn nominated_validator_that_does_not_lead_the_epoch_is_cosigner() {
         let (key_manager, _bundle_pvk) = test_keys();
-        let validators: HashMap<Address, Validator> = HashMap::new();
+        let validator_address: Address = key_manager.validator_address().try_into().unwrap();
+        let other_address: Address = {
+            let jwk = validator_key();
+            to_address(&jwk).unwrap().as_str().try_into().unwrap()
+        };
+        let validators: HashMap<Address, Validator> = [&validator_address, &other_address]
+            .into_iter()
+            .map(|address| {
+                (
+                    address.clone(),
+                    Validator {
+                        address: address.clone(),
+                        url: "https://validator.example.com".parse().unwrap(),
+                        stake: 1.into(),
+                    },
+                )
+            })
+            .collect();
         let nominated_validators = validators.keys().cloned().collect();
         let contract_state = create_contract_state(validators, nominated_validators);
+        let expected_role = if epoch_leader(&contract_state) == Some(&validator_address) {
+            ValidatorRole::Leader
+        } else {
+            ValidatorRole::Cosigner
+        };
 
         let client = {
             MockHttpClient::new(|a: &reqwest::Request, b: &reqwest::Request| a.url() == b.url())
@@ -234,11 +420,47 @@ mod test {
         rt.run_until(check_contract_updates(&ctx)).unwrap();
 
         assert_eq!(ctx.get_validator_state().current_epoch(), 1);
+        assert_eq!(ctx.get_validator_state().role(), expected_role);
     }
 
     #[test]
-    fn new_and_valid_slash_proposal_yields_call_to_vote_for_the_proposal() {
+    fn epoch_update_at_the_activation_block_height_yields_epoch_being_updated() {
         let (key_manager, _bundle_pvk) = test_keys();
+        let validators: HashMap<Address, Validator> = HashMap::new();
+        let nominated_validators = validators.keys().cloned().collect();
+        let contract_state = create_contract_state(validators, nominated_validators);
+
+        let client = {
+            MockHttpClient::new(|a: &reqwest::Request, b: &reqwest::Request| a.url() == b.url())
+                .when(|req: &reqwest::Request| {
+                    let url = "http://localhost:3000/validators/state";
+                    req.method() == Method::GET && &req.url().to_string() == url
+                })
+                .then(move |_: &reqwest::Request| {
+                    let initial_contract_state = serde_json::to_string(&contract_state).unwrap();
+                    http::response::Builder::new()
+                        .status(200)
+                        .body(initial_contract_state)
+                        .map(|res| reqwest::Response::from(res))
+                        .unwrap()
+                })
+        };
+
+        let ctx = test_context_with_http_client(key_manager, client);
+        ctx.get_validator_state().set_current_block(1);
+
+        let mut rt = LocalPool::new();
+        rt.run_until(check_contract_updates(&ctx)).unwrap();
+
+        assert_eq!(ctx.get_validator_state().current_epoch(), 1);
+    }
+
+    /// Contract state with a proposal to slash another validator for a
+    /// promise signed with `bundler_key`
+    fn state_with_slash_proposal(
+        key_manager: &InMemoryKeyManager,
+        bundler_key: &PKey<Private>,
+    ) -> State {
         let validator_address: Address = key_manager.validator_address().try_into().unwrap();
         let validators: HashMap<Address, Validator> = HashMap::from([(
             validator_address.clone(),
@@ -256,28 +478,75 @@ mod test {
             to_address(&jwk).unwrap().as_str().try_into().unwrap()
         };
 
-        {
-            let validator = validator_address_2.clone();
-            let proposal = Proposal {
-                id: "missing_tx".try_into().unwrap(),
-                size: 1,
-                fee: 1,
-                currency: "BTC".to_string(),
-                block: 1,
-                validator: validator.to_string(),
-                signature: "not_really_validated_here".to_string(),
-            };
-            contract_state.slash_proposals.insert(
-                validator.clone(),
-                (
-                    proposal,
-                    validator.clone(),
-                    2,
-                    "proposal_tx".try_into().unwrap(),
-                    Voting::Open(HashMap::from([(validator, Vote::For)])),
-                ),
-            );
-        }
+        let promise = test_message(
+            bundler_key,
+            1,
+            validator_address_2.to_string(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Z1",
+        );
+        let proposal = Proposal {
+            id: promise.id().try_into().unwrap(),
+            size: 0,
+            fee: 0,
+            currency: promise.currency().to_string(),
+            block: 1,
+            validator: promise.validator().to_string(),
+            signature: promise.signature().to_string(),
+        };
+        contract_state.slash_proposals.insert(
+            validator_address_2.clone(),
+            (
+                proposal,
+                validator_address_2.clone(),
+                2,
+                "proposal_tx".try_into().unwrap(),
+                Voting::Open(HashMap::from([(validator_address_2, Vote::For)])),
+            ),
+        );
+
+        contract_state
+    }
+
+    /// Record `bundler_key` as the key the bundler used up to block 1, the
+    /// promised block of the test proposal
+    fn record_bundler_key<Context: QueryContext>(ctx: &Context, bundler_key: &PKey<Private>) {
+        let n = bundler_key.rsa().unwrap().n().to_vec();
+        diesel::insert_into(bundler_key_history::table)
+            .values(&NewBundlerKey {
+                address: address_from_modulus(&n),
+                key_type: "rsa".to_string(),
+                key: n,
+                valid_from: Block(0),
+                valid_until: Some(Block(1)),
+            })
+            .execute(&ctx.get_db_connection())
+            .unwrap();
+    }
+
+    fn vote_request(req: &reqwest::Request, vote: Vote) -> bool {
+        let body: serde_json::Value = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|body| serde_json::from_slice(body).ok())
+            .unwrap_or_default();
+        req.method() == Method::POST
+            && req.url().as_str() == "http://localhost:3000/validators/vote"
+            && body["vote"] == serde_json::to_value(vote).unwrap()
+    }
+
+    fn vote_response(_: &reqwest::Request) -> reqwest::Response {
+        http::response::Builder::new()
+            .status(200)
+            .body(r#"{"status":"OK"}"#)
+            .map(reqwest::Response::from)
+            .unwrap()
+    }
+
+    #[test]
+    fn new_and_valid_slash_proposal_yields_call_to_vote_for_the_proposal() {
+        let (key_manager, bundler_key) = test_keys();
+        let contract_state = state_with_slash_proposal(&key_manager, &bundler_key);
+
         let client = {
             MockHttpClient::new(|a: &reqwest::Request, b: &reqwest::Request| a.url() == b.url())
                 .when(|req: &reqwest::Request| {
@@ -293,21 +562,23 @@ mod test {
                         .unwrap()
                 })
                 .when(|req: &reqwest::Request| {
-                    let url = "http://localhost:3000/validators/vote";
-                    req.method() == Method::POST && &req.url().to_string() == url
+                    req.method() == Method::POST && req.url().path() == "/graphql"
                 })
                 .then(|_: &reqwest::Request| {
-                    // TODO: vote response
+                    // Data item was bundled after the promised block
                     http::response::Builder::new()
                         .status(200)
-                        .body(r#"{"status":"OK"}"#)
+                        .body(r#"{"data": {"transactions": {"edges": [{"node": {"id": "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Z1", "bundledIn": {"id": "bundle"}, "block": {"id": "block", "timestamp": 10, "height": 2}}}]}}}"#)
                         .map(reqwest::Response::from)
                         .unwrap()
                 })
+                .when(|req: &reqwest::Request| vote_request(req, Vote::For))
+                .then(vote_response)
         };
 
         let ctx = test_context_with_http_client(key_manager, client.clone());
-        ctx.get_validator_state().set_current_block(1);
+        ctx.get_validator_state().set_current_block(2);
+        record_bundler_key(&ctx, &bundler_key);
 
         let mut rt = LocalPool::new();
         rt.run_until(check_contract_updates(&ctx)).unwrap();
@@ -320,13 +591,141 @@ mod test {
         drop(ctx);
 
         client.verify(|interactions| {
-            assert_eq!(interactions.len(), 2);
+            assert_eq!(interactions.len(), 3);
         });
     }
 
     #[test]
-    #[ignore = "not yet implemented"]
     fn new_but_invalid_slash_proposal_yields_call_to_vote_against_the_proposal() {
-        todo!()
+        let (key_manager, bundler_key) = test_keys();
+        let contract_state = state_with_slash_proposal(&key_manager, &bundler_key);
+
+        let client = {
+            MockHttpClient::new(|a: &reqwest::Request, b: &reqwest::Request| a.url() == b.url())
+                .when(|req: &reqwest::Request| {
+                    let url = "http://localhost:3000/validators/state";
+                    req.method() == Method::GET && &req.url().to_string() == url
+                })
+                .then(move |_: &reqwest::Request| {
+                    let initial_contract_state = serde_json::to_string(&contract_state).unwrap();
+                    http::response::Builder::new()
+                        .status(200)
+                        .body(initial_contract_state)
+                        .map(|res| reqwest::Response::from(res))
+                        .unwrap()
+                })
+                .when(|req: &reqwest::Request| {
+                    req.method() == Method::POST && req.url().path() == "/graphql"
+                })
+                .then(|_: &reqwest::Request| {
+                    // Data item was bundled by the promised block
+                    http::response::Builder::new()
+                        .status(200)
+                        .body(r#"{"data": {"transactions": {"edges": [{"node": {"id": "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Z1", "bundledIn": {"id": "bundle"}, "block": {"id": "block", "timestamp": 10, "height": 1}}}]}}}"#)
+                        .map(reqwest::Response::from)
+                        .unwrap()
+                })
+                .when(|req: &reqwest::Request| vote_request(req, Vote::Against))
+                .then(vote_response)
+        };
+
+        let ctx = test_context_with_http_client(key_manager, client.clone());
+        ctx.get_validator_state().set_current_block(2);
+        record_bundler_key(&ctx, &bundler_key);
+
+        let mut rt = LocalPool::new();
+        rt.run_until(check_contract_updates(&ctx)).unwrap();
+
+        drop(ctx);
+
+        client.verify(|interactions| {
+            assert_eq!(interactions.len(), 3);
+        });
+    }
+
+    #[test]
+    fn slash_proposal_for_unknown_data_item_is_not_voted_on() {
+        let (key_manager, bundler_key) = test_keys();
+        let contract_state = state_with_slash_proposal(&key_manager, &bundler_key);
+
+        let client = {
+            MockHttpClient::new(|a: &reqwest::Request, b: &reqwest::Request| a.url() == b.url())
+                .when(|req: &reqwest::Request| {
+                    let url = "http://localhost:3000/validators/state";
+                    req.method() == Method::GET && &req.url().to_string() == url
+                })
+                .then(move |_: &reqwest::Request| {
+                    let initial_contract_state = serde_json::to_string(&contract_state).unwrap();
+                    http::response::Builder::new()
+                        .status(200)
+                        .body(initial_contract_state)
+                        .map(|res| reqwest::Response::from(res))
+                        .unwrap()
+                })
+                .when(|req: &reqwest::Request| {
+                    req.method() == Method::POST && req.url().path() == "/graphql"
+                })
+                .then(|_: &reqwest::Request| {
+                    // Gateway didn't index the data item, or never saw it
+                    http::response::Builder::new()
+                        .status(200)
+                        .body(r#"{"data": {"transactions": {"edges": []}}}"#)
+                        .map(reqwest::Response::from)
+                        .unwrap()
+                })
+        };
+
+        let ctx = test_context_with_http_client(key_manager, client.clone());
+        ctx.get_validator_state().set_current_block(2);
+        record_bundler_key(&ctx, &bundler_key);
+
+        let mut rt = LocalPool::new();
+        rt.run_until(check_contract_updates(&ctx)).unwrap();
+
+        drop(ctx);
+
+        // No vote is cast
+        client.verify(|interactions| {
+            assert_eq!(interactions.len(), 2);
+        });
+    }
+
+    #[test]
+    fn slash_proposal_without_bundler_signature_yields_call_to_vote_against_the_proposal() {
+        let (key_manager, _bundler_key) = test_keys();
+        // Promise signed by someone else than the bundler
+        let (_, other_key) = test_keys();
+        let contract_state = state_with_slash_proposal(&key_manager, &other_key);
+
+        let client = {
+            MockHttpClient::new(|a: &reqwest::Request, b: &reqwest::Request| a.url() == b.url())
+                .when(|req: &reqwest::Request| {
+                    let url = "http://localhost:3000/validators/state";
+                    req.method() == Method::GET && &req.url().to_string() == url
+                })
+                .then(move |_: &reqwest::Request| {
+                    let initial_contract_state = serde_json::to_string(&contract_state).unwrap();
+                    http::response::Builder::new()
+                        .status(200)
+                        .body(initial_contract_state)
+                        .map(|res| reqwest::Response::from(res))
+                        .unwrap()
+                })
+                .when(|req: &reqwest::Request| vote_request(req, Vote::Against))
+                .then(vote_response)
+        };
+
+        let ctx = test_context_with_http_client(key_manager, client.clone());
+        ctx.get_validator_state().set_current_block(2);
+
+        let mut rt = LocalPool::new();
+        rt.run_until(check_contract_updates(&ctx)).unwrap();
+
+        drop(ctx);
+
+        // Gateway isn't asked about the data item
+        client.verify(|interactions| {
+            assert_eq!(interactions.len(), 2);
+        });
     }
 }
@@ -0,0 +1,66 @@
+use log::{error, info, warn};
+
+use crate::{
+    database::queries,
+    epoch_report::{self, EpochReportError},
+    key_manager,
+};
+
+use super::{error::ValidatorCronError, CronJobError};
+
+/// Sign a report for every finished epoch once all its promises are due
+pub async fn generate_epoch_reports<Context, KeyManager>(ctx: &Context) -> Result<(), CronJobError>
+where
+    Context: key_manager::KeyManagerAccess<KeyManager> + queries::QueryContext,
+    KeyManager: key_manager::KeyManager,
+{
+    let not_generated = |err: EpochReportError| {
+        error!("Failed to generate epoch report: {}", err);
+        CronJobError::ValidatorError(ValidatorCronError::EpochReportNotGenerated)
+    };
+
+    let conn = ctx.get_db_connection();
+    let state = ctx.get_validator_state();
+    let key_manager = ctx.get_key_manager();
+
+    let epochs = epoch_report::unreported_epochs(&conn).map_err(|err| not_generated(err.into()))?;
+    for epoch in epochs {
+        if epoch >= state.current_epoch() {
+            continue;
+        }
+
+        let report = epoch_report::build_report(
+            &conn,
+            epoch,
+            key_manager.validator_address(),
+            key_manager.bundler_address(),
+            state.current_block(),
+        )
+        .map_err(not_generated)?;
+        let report = match report {
+            Some(report) => report.sign(&key_manager).map_err(not_generated)?,
+            None => {
+                info!("Promises made in epoch {} are not due yet", epoch);
+                continue;
+            }
+        };
+
+        epoch_report::store_report(&conn, &report).map_err(|err| not_generated(err.into()))?;
+        if report.missing.is_empty() && report.late.is_empty() {
+            info!(
+                "Reported on epoch {}: {} transactions",
+                epoch, report.tx_count
+            );
+        } else {
+            warn!(
+                "Reported on epoch {}: {} transactions, {} late, {} missing",
+                epoch,
+                report.tx_count,
+                report.late.len(),
+                report.missing.len()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -9,11 +9,18 @@ pub enum ValidatorCronError {
     BundleNotInsertedInDB,
     TxInvalid,
     FileError,
-}
-
-#[derive(Debug, Display, Error, Clone)]
-pub enum TxsError {
-    TxNotFound,
+    StateNotSaved,
+    LeaderNotRecorded,
+    ReceiptsNotForwarded,
+    ReceiptsNotAggregated,
+    ReportNotGenerated,
+    EpochReportNotGenerated,
+    CheckpointNotLoaded,
+    CheckpointNotSaved,
+    SlashNotProposed,
+    InclusionNotTracked,
+    BundleNotVerified,
+    ReceiptNotVerified,
 }
 
 impl From<anyhow::Error> for ValidatorCronError {
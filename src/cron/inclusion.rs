@@ -0,0 +1,92 @@
+use log::{error, info, warn};
+
+use crate::{
+    context,
+    database::queries,
+    http,
+    inclusion::{self, Included, Inclusion},
+    state::ValidatorStateAccess,
+};
+
+use super::{arweave, error::ValidatorCronError, CronJobError};
+
+/// Blocks past the promised one before a transaction is looked up, gives the
+/// gateway time to index the bundle it was included in
+const INDEXING_MARGIN: u128 = 5;
+/// Blocks past the promised one after which transactions still not mined are
+/// given up on as missing, about a day
+const GIVE_UP_AFTER: u128 = 720;
+/// Most transactions looked up in one run
+const LOOKUP_BATCH: usize = 50;
+
+fn db_error(err: diesel::result::Error) -> CronJobError {
+    error!("Database query failed: {}", err);
+    CronJobError::ValidatorError(ValidatorCronError::InclusionNotTracked)
+}
+
+/// Look up cosigned transactions whose promised block has passed
+///
+/// Bundle of every data item and the block it was mined in are resolved
+/// through the Arweave gateway. Transactions included by the promised block
+/// are validated, the others are flagged as late or missing. Data items the
+/// gateway knows, but that aren't mined yet, stay pending.
+pub async fn track_inclusion<Context, HttpClient>(ctx: &Context) -> Result<(), CronJobError>
+where
+    Context: arweave::ArweaveContext<HttpClient>
+        + context::ArweaveAccess
+        + context::BundlerAccess
+        + queries::QueryContext,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+{
+    let current_block = ctx.get_validator_state().current_block();
+    let unresolved = inclusion::unresolved_transactions(
+        &ctx.get_db_connection(),
+        current_block,
+        INDEXING_MARGIN,
+        LOOKUP_BATCH,
+    )
+    .map_err(db_error)?;
+
+    for tx in unresolved {
+        let included = ctx
+            .arweave()
+            .get_data_item(ctx, &tx.id)
+            .await
+            .map_err(CronJobError::ArweaveError)?
+            .map(|item| Included {
+                bundle_id: item.bundled_in.map(|bundle| bundle.id),
+                block: item.block.map(|block| block.height),
+            });
+
+        let block_promised = tx.block_promised.0;
+        let give_up = block_promised.saturating_add(GIVE_UP_AFTER) < current_block;
+        let inclusion = inclusion::record_inclusion(
+            &ctx.get_db_connection(),
+            &tx,
+            included.as_ref(),
+            &ctx.bundler().address,
+            give_up,
+        )
+        .map_err(db_error)?;
+
+        match (inclusion, included.and_then(|included| included.block)) {
+            (Inclusion::InTime, _) => info!("Transaction {} included in time", tx.id),
+            (Inclusion::Late, Some(block)) => warn!(
+                "Transaction {} included at block {}, promised {}",
+                tx.id, block, block_promised
+            ),
+            (Inclusion::Missing, _) if give_up => warn!(
+                "Transaction {} not included, giving up on it, promised block {}",
+                tx.id, block_promised
+            ),
+            // Reported once, missing transactions are looked up on every run
+            (Inclusion::Missing, _) if !tx.missing => warn!(
+                "Transaction {} not included, promised block {}",
+                tx.id, block_promised
+            ),
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,256 @@
+use std::collections::HashSet;
+
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use log::{error, info, warn};
+use url::Url;
+
+use crate::{
+    database::{models::AuditLogEntry, queries, schema::audit_log},
+    key_manager,
+    leader::{self, receipt_epoch, Receipt},
+    server::{
+        error::ErrorCode,
+        routes::{leader::ReceiptsResponse, sign::SignRequest},
+    },
+    state::ValidatorRole,
+};
+
+use super::{error::ValidatorCronError, http, CronJobError};
+
+/// Most receipts sent to the leader at once
+const FORWARD_BATCH: i64 = 100;
+
+fn db_error(err: diesel::result::Error, failure: ValidatorCronError) -> CronJobError {
+    error!("Database query failed: {}", err);
+    CronJobError::ValidatorError(failure)
+}
+
+/// Forward receipts issued since the last run to the current leader
+///
+/// Receipts are read from the audit log, the last forwarded entry is stored so
+/// that each receipt is forwarded once. Entries are only marked forwarded once
+/// the leader accepted them or refused them for good. Receipts that didn't
+/// reach the leader, or were refused during a leader change, are sent again
+/// on the next run until the epoch they belong to is over.
+pub async fn forward_receipts<Context, HttpClient, KeyManager>(
+    ctx: &Context,
+) -> Result<(), CronJobError>
+where
+    Context: http::ClientAccess<HttpClient>
+        + key_manager::KeyManagerAccess<KeyManager>
+        + queries::QueryContext,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+    KeyManager: key_manager::KeyManager,
+{
+    let not_forwarded = |err| db_error(err, ValidatorCronError::ReceiptsNotForwarded);
+
+    let (leader, entries) = {
+        let conn = ctx.get_db_connection();
+        let leader = match leader::current_leader(&conn).map_err(not_forwarded)? {
+            Some(leader) => leader,
+            None => return Ok(()),
+        };
+        (leader, unforwarded_entries(&conn).map_err(not_forwarded)?)
+    };
+    let last_seq = match entries.last() {
+        Some(entry) => entry.seq,
+        None => return Ok(()),
+    };
+
+    let key_manager = ctx.get_key_manager();
+    let mut receipts = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let request: SignRequest = match serde_json::from_str(&entry.request) {
+            Ok(request) => request,
+            Err(err) => {
+                error!("Invalid request in audit log entry {}: {}", entry.seq, err);
+                continue;
+            }
+        };
+
+        // Public key of a rotated out key isn't known anymore
+        if request.validator() != key_manager.validator_address() {
+            warn!(
+                "Not forwarding receipt for {} issued with another key",
+                entry.tx_id
+            );
+            continue;
+        }
+
+        receipts.push((
+            entry.seq,
+            Receipt::new(request, key_manager.validator_public_key(), entry.signature),
+        ));
+    }
+
+    let state = ctx.get_validator_state();
+    let (leader_address, leader_url) = leader;
+    let mut retry = Vec::new();
+    if leader_address == key_manager.validator_address() {
+        // Own receipts don't need to be checked
+        let mut own = Vec::with_capacity(receipts.len());
+        for (seq, receipt) in &receipts {
+            match receipt_epoch(state, receipt.request.block()) {
+                Some(epoch) => own.push(receipt.to_new_receipt(epoch)),
+                None => retry.push((*seq, receipt)),
+            }
+        }
+        leader::store_receipts(&ctx.get_db_connection(), &own).map_err(not_forwarded)?;
+    } else if !receipts.is_empty() {
+        let leader_url = leader_url.ok_or_else(|| {
+            error!("URL of leader {} is not known", leader_address);
+            CronJobError::ValidatorError(ValidatorCronError::ReceiptsNotForwarded)
+        })?;
+        let sent: Vec<_> = receipts.iter().map(|(_, receipt)| receipt).collect();
+        let refused = send_receipts(ctx, &leader_url, &sent).await?;
+        retry.extend(
+            receipts
+                .iter()
+                .filter(|(_, receipt)| refused.contains(receipt.request.id()))
+                .map(|(seq, receipt)| (*seq, receipt)),
+        );
+    }
+
+    // Leader of an epoch that is over won't take its receipts anymore
+    let current_epoch = state.current_epoch();
+    let retry_from = retry
+        .into_iter()
+        .filter(|(_, receipt)| {
+            let open = state
+                .epoch_at(receipt.request.block())
+                .map_or(false, |epoch| epoch.seq >= current_epoch);
+            if !open {
+                warn!(
+                    "Not forwarding receipt for {} anymore, its epoch is over",
+                    receipt.request.id()
+                );
+            }
+            open
+        })
+        .map(|(seq, _)| seq)
+        .min();
+
+    let forwarded = retry_from.map_or(last_seq, |seq| seq - 1);
+    leader::set_forwarded_seq(&ctx.get_db_connection(), forwarded).map_err(not_forwarded)?;
+    info!("Forwarded receipts up to audit log entry {}", forwarded);
+
+    Ok(())
+}
+
+fn unforwarded_entries(conn: &PgConnection) -> QueryResult<Vec<AuditLogEntry>> {
+    let after = leader::forwarded_seq(conn)?.unwrap_or(-1);
+    audit_log::table
+        .filter(audit_log::seq.gt(after))
+        .order(audit_log::seq.asc())
+        .limit(FORWARD_BATCH)
+        .load::<AuditLogEntry>(conn)
+}
+
+/// Send receipts to the leader
+///
+/// Returns ids of the receipts the leader refused for now, but could take
+/// later.
+async fn send_receipts<Context, HttpClient>(
+    ctx: &Context,
+    leader_url: &str,
+    receipts: &[&Receipt],
+) -> Result<HashSet<String>, CronJobError>
+where
+    Context: http::ClientAccess<HttpClient>,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+{
+    let not_forwarded = CronJobError::ValidatorError(ValidatorCronError::ReceiptsNotForwarded);
+
+    let url = Url::parse(leader_url)
+        .and_then(|url| url.join("leader/receipts"))
+        .map_err(|err| {
+            error!("Invalid leader URL {}: {}", leader_url, err);
+            not_forwarded.clone()
+        })?;
+
+    let req = http::request::Builder::new()
+        .method(http::Method::POST)
+        .uri(url.as_str())
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(receipts).expect("Failed to serialize receipts"))
+        .map(|req| {
+            reqwest::Request::try_from(req)
+                .expect("Failed to convert http::request::Request into reqwest::Request")
+        })
+        .expect("Failed to build request for forwarding receipts");
+
+    let res = ctx.get_http_client().execute(req).await.map_err(|err| {
+        error!("Failed to forward receipts to {}: {:?}", url, err);
+        not_forwarded.clone()
+    })?;
+
+    if !res.status().is_success() {
+        error!("Leader {} refused receipts: {}", url, res.status());
+        return Err(not_forwarded);
+    }
+
+    let res: ReceiptsResponse = res.json().await.map_err(|err| {
+        error!("Failed to deserialize the response: {:?}", err);
+        not_forwarded.clone()
+    })?;
+    let mut refused = HashSet::new();
+    for rejected in res.rejected {
+        warn!(
+            "Leader rejected receipt for {}: {}",
+            rejected.id, rejected.error.message
+        );
+        if matches!(
+            rejected.error.code,
+            ErrorCode::NotLeader | ErrorCode::NotReady | ErrorCode::Overloaded
+        ) {
+            refused.insert(rejected.id);
+        }
+    }
+
+    Ok(refused)
+}
+
+/// Aggregate receipts of the epoch this validator is leading
+pub async fn aggregate_receipts<Context>(ctx: &Context) -> Result<(), CronJobError>
+where
+    Context: queries::QueryContext,
+{
+    let state = ctx.get_validator_state();
+    if state.role() != ValidatorRole::Leader {
+        return Ok(());
+    }
+
+    let aggregated = leader::aggregate_epoch(&ctx.get_db_connection(), state.current_epoch())
+        .map_err(|err| db_error(err, ValidatorCronError::ReceiptsNotAggregated))?;
+    info!(
+        "Aggregated receipts of {} transactions in epoch {}",
+        aggregated,
+        state.current_epoch()
+    );
+
+    Ok(())
+}
+
+/// Report on every finished epoch this validator led
+pub async fn generate_reports<Context>(ctx: &Context) -> Result<(), CronJobError>
+where
+    Context: queries::QueryContext,
+{
+    let not_generated = |err| db_error(err, ValidatorCronError::ReportNotGenerated);
+
+    let conn = ctx.get_db_connection();
+    let current_epoch = ctx.get_validator_state().current_epoch();
+    for epoch in leader::unreported_epochs(&conn).map_err(not_generated)? {
+        if epoch >= current_epoch {
+            continue;
+        }
+
+        let report = leader::generate_report(&conn, epoch).map_err(not_generated)?;
+        info!(
+            "Reported on epoch {}: {} transactions, {} receipts, {} conflicting",
+            epoch, report.tx_count, report.receipt_count, report.conflicting_count
+        );
+    }
+
+    Ok(())
+}
@@ -2,23 +2,30 @@ pub mod arweave;
 mod bundle;
 mod clear_transactions;
 mod contract;
+mod epoch_report;
 mod error;
+mod inclusion;
+mod leader;
+pub mod scheduler;
 mod slasher;
-mod transactions;
 mod validate;
 
 use crate::{
     context,
     contract_gateway::{self, ContractGatewayError},
     database::queries,
-    http, key_manager,
+    events, http, key_manager,
+    state::{self, StateSnapshot},
 };
 use derive_more::{Display, Error};
-use futures::{join, Future};
-use log::{error, info};
-use std::time::Duration;
+use futures::join;
+use log::{error, info, warn};
 
-use self::{arweave::ArweaveError, error::ValidatorCronError};
+use self::{
+    arweave::ArweaveError,
+    error::ValidatorCronError,
+    scheduler::{schedule, JobConfig, JobsAccess},
+};
 
 #[derive(Debug, Display, Error, Clone, PartialEq)]
 pub enum CronJobError {
@@ -27,6 +34,32 @@ pub enum CronJobError {
     ValidatorError(ValidatorCronError),
 }
 
+const CONTRACT_UPDATES: &str = "contract-updates";
+const NETWORK_INFO: &str = "network-info";
+const VALIDATE_BUNDLER: &str = "validate-bundler";
+const TRACK_INCLUSION: &str = "track-inclusion";
+const SLASH_PROPOSALS: &str = "slash-proposals";
+const CLEAR_TRANSACTIONS: &str = "clear-transactions";
+const FORWARD_RECEIPTS: &str = "forward-receipts";
+const AGGREGATE_RECEIPTS: &str = "aggregate-receipts";
+const LEADER_REPORTS: &str = "leader-reports";
+const EPOCH_REPORTS: &str = "epoch-reports";
+
+/// Every job scheduled by [`run_crons`], overrides for any other name are
+/// most likely typos
+const JOB_NAMES: [&str; 10] = [
+    CONTRACT_UPDATES,
+    NETWORK_INFO,
+    VALIDATE_BUNDLER,
+    TRACK_INCLUSION,
+    SLASH_PROPOSALS,
+    CLEAR_TRANSACTIONS,
+    FORWARD_RECEIPTS,
+    AGGREGATE_RECEIPTS,
+    LEADER_REPORTS,
+    EPOCH_REPORTS,
+];
+
 // Update contract state
 pub async fn run_crons<Context, HttpClient, KeyManager>(ctx: Context)
 where
@@ -35,7 +68,9 @@ where
         + context::BundlerAccess
         + context::ValidatorAddressAccess
         + contract_gateway::ContractGatewayAccess
+        + events::EventsAccess
         + http::ClientAccess<HttpClient>
+        + JobsAccess
         + key_manager::KeyManagerAccess<KeyManager>
         + queries::QueryContext,
     HttpClient: http::Client<
@@ -46,48 +81,92 @@ where
     KeyManager: key_manager::KeyManager,
 {
     info!("Validator starting ...");
+    warn_unknown_overrides(&ctx);
     join!(
-        create_cron(
+        schedule(
+            &ctx,
+            CONTRACT_UPDATES,
+            JobConfig::every(30),
+            contract::check_contract_updates::<_, HttpClient>
+        ),
+        schedule(
+            &ctx,
+            NETWORK_INFO,
+            JobConfig::every(30),
+            arweave::sync_network_info
+        ),
+        schedule(
+            &ctx,
+            VALIDATE_BUNDLER,
+            JobConfig::every(2 * 60).timeout(10 * 60),
+            validate::validate::<_, HttpClient, KeyManager>
+        ),
+        schedule(
             &ctx,
-            "check contract updates",
-            contract::check_contract_updates,
-            30
+            TRACK_INCLUSION,
+            JobConfig::every(60).timeout(5 * 60),
+            inclusion::track_inclusion
         ),
-        create_cron(&ctx, "sync network info", arweave::sync_network_info, 30),
-        // create_cron(&ctx, "validate bundler", validate::validate, 2 * 60),
-        create_cron(
+        schedule(
             &ctx,
-            "validate transactions",
-            validate::validate_transactions,
-            30
+            SLASH_PROPOSALS,
+            JobConfig::every(60),
+            slasher::propose_slashes
         ),
-        create_cron(
+        schedule(
             &ctx,
-            "clear old transactions",
-            clear_transactions::clear_old_transactions,
-            180
+            CLEAR_TRANSACTIONS,
+            JobConfig::every(180).timeout(60),
+            clear_transactions::clear_old_transactions
+        ),
+        schedule(
+            &ctx,
+            FORWARD_RECEIPTS,
+            JobConfig::every(30),
+            leader::forward_receipts::<_, HttpClient, KeyManager>
+        ),
+        schedule(
+            &ctx,
+            AGGREGATE_RECEIPTS,
+            JobConfig::every(60),
+            leader::aggregate_receipts
+        ),
+        schedule(
+            &ctx,
+            LEADER_REPORTS,
+            JobConfig::every(60),
+            leader::generate_reports
+        ),
+        schedule(
+            &ctx,
+            EPOCH_REPORTS,
+            JobConfig::every(60),
+            epoch_report::generate_epoch_reports::<_, KeyManager>
         )
     );
 }
 
-async fn create_cron<'a, Context, HttpClient, F>(
-    ctx: &'a Context,
-    description: &str,
-    f: impl Fn(&'a Context) -> F,
-    sleep: u64,
-) where
-    F: Future<Output = Result<(), CronJobError>> + 'a,
-    HttpClient: http::Client,
-    Context: http::ClientAccess<HttpClient>,
+fn warn_unknown_overrides<Context>(ctx: &Context)
+where
+    Context: JobsAccess,
 {
-    loop {
-        info!("Task running - {}", description);
-        match f(ctx).await {
-            Ok(_) => info!("Task finished - {}", description),
-            Err(e) => error!("Task error - {} with {}", description, e),
-        };
+    for name in ctx.jobs().unknown_overrides(&JOB_NAMES) {
+        warn!("Schedule configured for unknown job {}", name);
+    }
+}
 
-        info!("Task sleeping for {} seconds - {}", sleep, description);
-        tokio::time::sleep(Duration::from_secs(sleep)).await;
+/// Persist validator state if it changed since `before` was taken
+fn save_state_if_changed<Context>(ctx: &Context, before: &StateSnapshot) -> Result<(), CronJobError>
+where
+    Context: queries::QueryContext,
+{
+    let after = ctx.get_validator_state().snapshot();
+    if after != *before {
+        state::save_state(&ctx.get_db_connection(), &after).map_err(|err| {
+            error!("Failed to save validator state: {}", err);
+            CronJobError::ValidatorError(ValidatorCronError::StateNotSaved)
+        })?;
     }
+
+    Ok(())
 }
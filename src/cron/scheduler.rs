@@ -0,0 +1,389 @@
+//! Periodic jobs with timeouts, jitter and failure backoff
+//!
+//! Every job runs in its own loop, so a run never overlaps the previous run
+//! of the same job. A run that takes longer than the job timeout is dropped
+//! and counts as a failure. After a failure the next run is delayed
+//! exponentially, up to the job's maximum backoff. Random jitter is added to
+//! every delay so that jobs started together drift apart. Outcome of the last
+//! run of every job is kept in a [`JobRegistry`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use futures::Future;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::CronJobError;
+
+/// Longest delay after repeated failures, unless configured otherwise
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+pub trait JobsAccess {
+    fn jobs(&self) -> &JobRegistry;
+}
+
+/// Schedule of a job
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct JobConfig {
+    #[serde(serialize_with = "ser_secs")]
+    pub interval: Duration,
+    #[serde(serialize_with = "ser_secs")]
+    pub timeout: Duration,
+    /// Upper bound of the random delay added to every wait
+    #[serde(serialize_with = "ser_secs")]
+    pub jitter: Duration,
+    #[serde(serialize_with = "ser_secs")]
+    pub max_backoff: Duration,
+}
+
+impl JobConfig {
+    /// Run every `secs` seconds, a run may take as long as the interval
+    pub fn every(secs: u64) -> Self {
+        let interval = Duration::from_secs(secs);
+        Self {
+            interval,
+            timeout: interval,
+            jitter: interval / 10,
+            max_backoff: DEFAULT_MAX_BACKOFF.max(interval),
+        }
+    }
+
+    pub fn timeout(self, secs: u64) -> Self {
+        Self {
+            timeout: Duration::from_secs(secs),
+            ..self
+        }
+    }
+
+    fn with_overrides(self, overrides: &JobOverrides) -> Self {
+        let secs = |val: Option<u64>, default| val.map(Duration::from_secs).unwrap_or(default);
+        Self {
+            interval: secs(overrides.interval, self.interval),
+            timeout: secs(overrides.timeout, self.timeout),
+            jitter: secs(overrides.jitter, self.jitter),
+            max_backoff: secs(overrides.max_backoff, self.max_backoff),
+        }
+    }
+
+    /// Wait before the next run, without jitter
+    pub fn delay(&self, consecutive_failures: u32) -> Duration {
+        if consecutive_failures == 0 {
+            return self.interval;
+        }
+
+        let factor = 1u32 << consecutive_failures.min(16);
+        self.interval
+            .saturating_mul(factor)
+            .min(self.max_backoff.max(self.interval))
+    }
+}
+
+fn ser_secs<S: serde::Serializer>(val: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(val.as_secs())
+}
+
+/// Operator overrides of a job schedule, in seconds
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct JobOverrides {
+    pub interval: Option<u64>,
+    pub timeout: Option<u64>,
+    pub jitter: Option<u64>,
+    pub max_backoff: Option<u64>,
+}
+
+/// Overrides by job name
+pub type JobsConfig = HashMap<String, JobOverrides>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobOutcome {
+    Success,
+    Failure,
+    Timeout,
+}
+
+/// Schedule and last run of a job
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct JobStatus {
+    pub config: JobConfig,
+    pub running: bool,
+    pub last_start: Option<DateTime<Utc>>,
+    pub last_finish: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    pub last_outcome: Option<JobOutcome>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+impl JobStatus {
+    fn new(config: JobConfig) -> Self {
+        Self {
+            config,
+            running: false,
+            last_start: None,
+            last_finish: None,
+            last_duration_ms: None,
+            last_outcome: None,
+            last_error: None,
+            consecutive_failures: 0,
+            next_run: None,
+        }
+    }
+}
+
+/// Status of every scheduled job, clones share the same registry
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    overrides: Arc<JobsConfig>,
+    jobs: Arc<RwLock<BTreeMap<String, JobStatus>>>,
+}
+
+impl JobRegistry {
+    pub fn new(overrides: JobsConfig) -> Self {
+        Self {
+            overrides: Arc::new(overrides),
+            jobs: Arc::default(),
+        }
+    }
+
+    /// Register a job with `defaults`, applying operator overrides
+    pub fn register(&self, name: &str, defaults: JobConfig) -> JobConfig {
+        let config = match self.overrides.get(name) {
+            Some(overrides) => defaults.with_overrides(overrides),
+            None => defaults,
+        };
+        self.jobs
+            .write()
+            .expect("Failed to lock job registry")
+            .insert(name.to_string(), JobStatus::new(config));
+        config
+    }
+
+    /// Names of overridden jobs that are not among `known`
+    pub fn unknown_overrides(&self, known: &[&str]) -> Vec<String> {
+        let mut unknown: Vec<String> = self
+            .overrides
+            .keys()
+            .filter(|name| !known.contains(&name.as_str()))
+            .cloned()
+            .collect();
+        unknown.sort();
+        unknown
+    }
+
+    pub fn get(&self, name: &str) -> Option<JobStatus> {
+        self.jobs
+            .read()
+            .expect("Failed to lock job registry")
+            .get(name)
+            .cloned()
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, JobStatus> {
+        self.jobs
+            .read()
+            .expect("Failed to lock job registry")
+            .clone()
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut JobStatus)) {
+        if let Some(status) = self
+            .jobs
+            .write()
+            .expect("Failed to lock job registry")
+            .get_mut(name)
+        {
+            f(status);
+        }
+    }
+}
+
+/// Run `job` once within `timeout` and record the outcome
+///
+/// Returns the number of consecutive failures after this run.
+pub async fn run_once<F>(registry: &JobRegistry, name: &str, timeout: Duration, job: F) -> u32
+where
+    F: Future<Output = Result<(), CronJobError>>,
+{
+    info!("Task running - {}", name);
+    registry.update(name, |status| {
+        status.running = true;
+        status.last_start = Some(Utc::now());
+        status.next_run = None;
+    });
+
+    let started = Instant::now();
+    let (outcome, err) = match tokio::time::timeout(timeout, job).await {
+        Ok(Ok(())) => {
+            info!("Task finished - {}", name);
+            (JobOutcome::Success, None)
+        }
+        Ok(Err(err)) => {
+            error!("Task error - {} with {}", name, err);
+            (JobOutcome::Failure, Some(err.to_string()))
+        }
+        Err(_) => {
+            error!("Task timed out after {:?} - {}", timeout, name);
+            (
+                JobOutcome::Timeout,
+                Some(format!("timed out after {:?}", timeout)),
+            )
+        }
+    };
+    let duration = started.elapsed();
+
+    let mut failures = 0;
+    registry.update(name, |status| {
+        status.running = false;
+        status.last_finish = Some(Utc::now());
+        status.last_duration_ms = Some(duration.as_millis() as u64);
+        status.last_outcome = Some(outcome);
+        status.last_error = err;
+        status.consecutive_failures = match outcome {
+            JobOutcome::Success => 0,
+            _ => status.consecutive_failures + 1,
+        };
+        failures = status.consecutive_failures;
+    });
+
+    failures
+}
+
+/// Run `f` forever on the schedule registered under `name`
+pub async fn schedule<'a, Context, F>(
+    ctx: &'a Context,
+    name: &str,
+    defaults: JobConfig,
+    f: impl Fn(&'a Context) -> F,
+) where
+    F: Future<Output = Result<(), CronJobError>> + 'a,
+    Context: JobsAccess,
+{
+    let registry = ctx.jobs();
+    let config = registry.register(name, defaults);
+
+    // Spread jobs started at the same time
+    tokio::time::sleep(random_jitter(config.jitter)).await;
+
+    loop {
+        let failures = run_once(registry, name, config.timeout, f(ctx)).await;
+
+        let delay = config.delay(failures) + random_jitter(config.jitter);
+        if failures > 0 {
+            warn!(
+                "Task failed {} times in a row, retrying in {} seconds - {}",
+                failures,
+                delay.as_secs(),
+                name
+            );
+        } else {
+            info!("Task sleeping for {} seconds - {}", delay.as_secs(), name);
+        }
+        registry.update(name, |status| {
+            status.next_run = chrono::Duration::from_std(delay)
+                .ok()
+                .map(|delay| Utc::now() + delay);
+        });
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Random duration shorter than `max`
+fn random_jitter(max: Duration) -> Duration {
+    let max_millis = max.as_millis() as u64;
+    if max_millis == 0 {
+        return Duration::ZERO;
+    }
+
+    let mut bytes = [0u8; 8];
+    openssl::rand::rand_bytes(&mut bytes).expect("Failed to generate random jitter");
+    Duration::from_millis(u64::from_le_bytes(bytes) % max_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::cron::{error::ValidatorCronError, CronJobError};
+
+    use super::{random_jitter, run_once, JobConfig, JobOutcome, JobOverrides, JobRegistry};
+
+    #[test]
+    fn failures_back_off_exponentially_up_to_the_limit() {
+        let config = JobConfig::every(30);
+
+        assert_eq!(config.delay(0), Duration::from_secs(30));
+        assert_eq!(config.delay(1), Duration::from_secs(60));
+        assert_eq!(config.delay(3), Duration::from_secs(240));
+        assert_eq!(config.delay(5), Duration::from_secs(600));
+        assert_eq!(config.delay(100), Duration::from_secs(600));
+
+        for _ in 0..100 {
+            assert!(random_jitter(config.jitter) < config.jitter);
+        }
+        assert_eq!(random_jitter(Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn operator_overrides_replace_defaults() {
+        let registry = JobRegistry::new(HashMap::from([
+            (
+                "network-info".to_string(),
+                JobOverrides {
+                    interval: Some(10),
+                    jitter: Some(0),
+                    ..JobOverrides::default()
+                },
+            ),
+            ("no-such-job".to_string(), JobOverrides::default()),
+        ]));
+
+        let config = registry.register("network-info", JobConfig::every(30).timeout(5));
+        assert_eq!(config.interval, Duration::from_secs(10));
+        assert_eq!(config.timeout, Duration::from_secs(5));
+        assert_eq!(config.jitter, Duration::ZERO);
+
+        assert_eq!(
+            registry.unknown_overrides(&["network-info"]),
+            vec!["no-such-job".to_string()]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn outcome_of_every_run_is_recorded() {
+        let registry = JobRegistry::default();
+        registry.register("job", JobConfig::every(30));
+
+        let failures = run_once(&registry, "job", Duration::from_secs(1), async {
+            Err(CronJobError::ValidatorError(ValidatorCronError::TxNotFound))
+        })
+        .await;
+        assert_eq!(failures, 1);
+
+        let failures = run_once(&registry, "job", Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        })
+        .await;
+        assert_eq!(failures, 2);
+        let status = registry.get("job").unwrap();
+        assert_eq!(status.last_outcome, Some(JobOutcome::Timeout));
+        assert!(!status.running);
+        assert!(status.last_start.is_some() && status.last_finish.is_some());
+
+        let failures = run_once(&registry, "job", Duration::from_secs(1), async { Ok(()) }).await;
+        assert_eq!(failures, 0);
+        let status = registry.get("job").unwrap();
+        assert_eq!(status.last_outcome, Some(JobOutcome::Success));
+        assert_eq!(status.last_error, None);
+        assert_eq!(status.consecutive_failures, 0);
+    }
+}
@@ -1,6 +1,296 @@
-use crate::bundler::Bundler;
+use diesel::PgConnection;
+use log::{error, info, warn};
+
+use crate::{
+    context, contract_gateway, database::queries, http, slashing, state::ValidatorStateAccess,
+};
+
+use super::{arweave, error::ValidatorCronError, CronJobError};
+
+/// Blocks past the promised one before a transaction is considered missing,
+/// gives the gateway time to index the bundle it was included in
+const INDEXING_MARGIN: u128 = 5;
+/// Most overdue transactions checked in one run
+const CHECK_BATCH: usize = 50;
+
+fn db_error(err: diesel::result::Error) -> CronJobError {
+    error!("Database query failed: {}", err);
+    CronJobError::ValidatorError(ValidatorCronError::SlashNotProposed)
+}
+
+/// Propose to slash for cosigned transactions that weren't included on time
+///
+/// Transactions past their promised block are looked up on Arweave, a
+/// proposal is recorded for the ones that weren't included by the promised
+/// block. Recorded proposals are then submitted to the contract, unless the
+/// contract already holds a proposal for the same transaction.
+pub async fn propose_slashes<Context, HttpClient>(ctx: &Context) -> Result<(), CronJobError>
+where
+    Context: arweave::ArweaveContext<HttpClient>
+        + context::ArweaveAccess
+        + contract_gateway::ContractGatewayAccess
+        + http::ClientAccess<HttpClient>
+        + queries::QueryContext,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+{
+    record_overdue(ctx).await?;
+    submit_recorded(ctx).await
+}
+
+async fn record_overdue<Context, HttpClient>(ctx: &Context) -> Result<(), CronJobError>
+where
+    Context: arweave::ArweaveContext<HttpClient> + context::ArweaveAccess + queries::QueryContext,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+{
+    let current_block = ctx.get_validator_state().current_block();
+    let overdue = slashing::overdue_transactions(
+        &ctx.get_db_connection(),
+        current_block,
+        INDEXING_MARGIN,
+        CHECK_BATCH,
+    )
+    .map_err(db_error)?;
+
+    for tx in overdue {
+        let block_promised = tx.block_promised.0;
+        let included_at = ctx
+            .arweave()
+            .get_data_item(ctx, &tx.id)
+            .await
+            .map_err(CronJobError::ArweaveError)?
+            .and_then(|item| item.block)
+            .map(|block| block.height);
+
+        let conn = ctx.get_db_connection();
+        match included_at {
+            Some(height) if height <= block_promised => {
+                slashing::mark_included(&conn, &tx.id, height).map_err(db_error)?;
+                continue;
+            }
+            Some(height) => warn!(
+                "Transaction {} included at block {}, promised {}",
+                tx.id, height, block_promised
+            ),
+            None => warn!(
+                "Transaction {} not included, promised block {}",
+                tx.id, block_promised
+            ),
+        }
+
+        record_proposal(&conn, &tx.id)?;
+    }
+
+    Ok(())
+}
+
+fn record_proposal(conn: &PgConnection, tx_id: &str) -> Result<(), CronJobError> {
+    let promise = match slashing::cosigned_promise(conn, tx_id).map_err(db_error)? {
+        Some(promise) => promise,
+        None => {
+            error!("Promise for {} is missing from the audit log", tx_id);
+            return Ok(());
+        }
+    };
+    let proposal = match slashing::proposal_from_request(&promise) {
+        Some(proposal) => proposal,
+        None => {
+            error!(
+                "Can't propose to slash for invalid transaction id {}",
+                tx_id
+            );
+            return Ok(());
+        }
+    };
+
+    if slashing::record_proposal(conn, &proposal).map_err(db_error)? {
+        info!("Recorded slash proposal for {}", tx_id);
+    }
+
+    Ok(())
+}
+
+async fn submit_recorded<Context, HttpClient>(ctx: &Context) -> Result<(), CronJobError>
+where
+    Context: contract_gateway::ContractGatewayAccess
+        + http::ClientAccess<HttpClient>
+        + queries::QueryContext,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+{
+    let proposals = slashing::unsubmitted_proposals(&ctx.get_db_connection()).map_err(db_error)?;
+    if proposals.is_empty() {
+        return Ok(());
+    }
+
+    let contract_gateway = ctx.contract_gateway();
+    // Submission may have gone through before it could be marked submitted
+    let state = contract_gateway
+        .get_current_state(ctx)
+        .await
+        .map_err(CronJobError::ContractGatewayError)?;
+
+    for proposal in proposals {
+        let known = state
+            .slash_proposals
+            .values()
+            .any(|(known, ..)| *known.id == *proposal.id);
+
+        if known {
+            info!("Slash proposal for {} already submitted", &*proposal.id);
+        } else {
+            contract_gateway
+                .propose_slash(ctx, &proposal)
+                .await
+                .map_err(CronJobError::ContractGatewayError)?;
+            info!("Submitted slash proposal for {}", &*proposal.id);
+        }
+
+        slashing::mark_submitted(&ctx.get_db_connection(), &proposal.id).map_err(db_error)?;
+    }
 
-// TODO: implement slash voting
-pub fn vote_slash(_bundler: &Bundler) -> Result<(), ()> {
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use bundlr_contracts_validators::State;
+    use diesel::RunQueryDsl;
+    use http::Method;
+    use reqwest::{Request, Response};
+
+    use crate::{
+        audit_log,
+        context::test_utils::test_context_with_http_client,
+        database::{
+            models::{Block, Epoch, NewTransaction},
+            queries::QueryContext,
+            schema::transactions,
+        },
+        http::reqwest::mock::MockHttpClient,
+        key_manager::{test_utils::test_keys, KeyManager},
+        server::routes::sign::test_utils::test_message,
+        slashing,
+        state::ValidatorStateAccess,
+    };
+
+    use super::propose_slashes;
+
+    const TX_ID: &str = "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-S1";
+
+    fn contract_state() -> State {
+        State {
+            bundler: "bundler_address".try_into().unwrap(),
+            bundlers_contract: "bundlers_contract_address".try_into().unwrap(),
+            epoch: bundlr_contracts_validators::Epoch {
+                seq: 1,
+                tx: "tx1".try_into().unwrap(),
+                height: 1,
+            },
+            epoch_duration: 2,
+            minimum_stake: 1.into(),
+            token: "token_contract_address".try_into().unwrap(),
+            max_num_nominated_validators: 10,
+            validators: HashMap::new(),
+            nominated_validators: Vec::new(),
+            slash_proposal_lifetime: 10,
+            slash_proposals: HashMap::new(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn missing_transaction_is_proposed_for_slashing_once() {
+        let submitted = Arc::new(AtomicUsize::new(0));
+        let client = {
+            let submitted = submitted.clone();
+            MockHttpClient::new(|a: &Request, b: &Request| a.url() == b.url())
+                .when(|req: &Request| {
+                    req.method() == Method::POST && req.url().path() == "/graphql"
+                })
+                .then(|_: &Request| {
+                    // Gateway doesn't know any data item
+                    let response = http::response::Builder::new()
+                        .status(200)
+                        .body(r#"{"data": {"transactions": {"edges": []}}}"#)
+                        .unwrap();
+                    Response::from(response)
+                })
+                .when(|req: &Request| {
+                    let url = "http://localhost:3000/validators/state";
+                    req.method() == Method::GET && req.url().as_str() == url
+                })
+                .then(|_: &Request| {
+                    let response = http::response::Builder::new()
+                        .status(200)
+                        .body(serde_json::to_string(&contract_state()).unwrap())
+                        .unwrap();
+                    Response::from(response)
+                })
+                .when(|req: &Request| {
+                    let url = "http://localhost:3000/validators/slash";
+                    req.method() == Method::POST && req.url().as_str() == url
+                })
+                .then(move |req: &Request| {
+                    let body: serde_json::Value = req
+                        .body()
+                        .and_then(|body| body.as_bytes())
+                        .and_then(|body| serde_json::from_slice(body).ok())
+                        .unwrap_or_default();
+                    if body["id"] == TX_ID {
+                        submitted.fetch_add(1, Ordering::SeqCst);
+                    }
+                    let response = http::response::Builder::new()
+                        .status(200)
+                        .body(r#"{"status":"OK"}"#)
+                        .unwrap();
+                    Response::from(response)
+                })
+        };
+
+        let (key_manager, bundler_key) = test_keys();
+        let promise = test_message(
+            &bundler_key,
+            10,
+            key_manager.validator_address().to_string(),
+            TX_ID,
+        );
+        let ctx = test_context_with_http_client(key_manager, client);
+        ctx.get_validator_state().set_current_block(100);
+
+        {
+            let conn = ctx.get_db_connection();
+            diesel::insert_into(transactions::table)
+                .values(&NewTransaction {
+                    id: TX_ID.to_string(),
+                    epoch: Epoch(1),
+                    block_promised: Block(10),
+                    block_actual: None,
+                    signature: "validator_signature".as_bytes().to_vec(),
+                    validated: false,
+                    bundle_id: None,
+                    size: Some(0),
+                    fee: Some("0".to_string()),
+                    currency: Some("FOO".to_string()),
+                    bundler_signature: Some(promise.signature().as_bytes().to_vec()),
+                })
+                .execute(&conn)
+                .unwrap();
+            audit_log::append(&conn, &promise, "validator_signature").unwrap();
+        }
+
+        propose_slashes(&ctx).await.unwrap();
+        propose_slashes(&ctx).await.unwrap();
+
+        assert_eq!(submitted.load(Ordering::SeqCst), 1);
+        let stored = slashing::load_proposal(&ctx.get_db_connection(), TX_ID)
+            .unwrap()
+            .unwrap();
+        assert!(stored.submitted_at.is_some());
+    }
+}
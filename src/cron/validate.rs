@@ -17,7 +17,7 @@ where
     KeyManager: key_manager::KeyManager,
 {
     match ctx.get_validator_state().role() {
-        ValidatorRole::Cosigner => validate_bundler(&*ctx)
+        ValidatorRole::Cosigner | ValidatorRole::Leader => validate_bundler(&*ctx)
             .await
             .map_err(CronJobError::ValidatorError)?,
         ValidatorRole::Idle => (),
@@ -25,16 +25,3 @@ where
 
     Ok(())
 }
-
-pub async fn validate_transactions<Context, HttpClient>(ctx: &Context) -> Result<(), CronJobError>
-where
-    Context: context::BundlerAccess + http::ClientAccess<HttpClient>,
-    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
-{
-    let http_client = ctx.get_http_client();
-    super::bundle::validate_transactions(http_client, ctx.bundler())
-        .await
-        .map_err(CronJobError::ValidatorError)?;
-
-    Ok(())
-}
@@ -1,12 +1,21 @@
+use super::schema::audit_log;
 use super::schema::bundle;
+use super::schema::bundler_key_history;
+use super::schema::equivocations;
+use super::schema::leader_reports;
+use super::schema::receipt_aggregates;
+use super::schema::receipts;
+use super::schema::scheduled_epochs;
+use super::schema::slash_proposals;
 use super::schema::transactions;
+use super::schema::validator_state;
 use diesel::pg::Pg;
 use diesel::sql_types::Binary;
 use diesel::types::FromSql;
 use diesel::types::IsNull;
 use diesel::types::ToSql;
 use diesel::{Insertable, Queryable};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -55,6 +64,8 @@ impl ToSql<Binary, Pg> for Epoch {
     }
 }
 
+/// Stored as 16 little endian bytes, `transactions.block_promised_height`
+/// decodes the low 8 of them
 #[derive(AsExpression, Clone, Copy, Debug, FromSqlRow, PartialEq, Serialize)]
 #[diesel(foreigh_type)]
 #[sql_type = "Binary"]
@@ -67,7 +78,7 @@ impl TryFrom<&[u8]> for Block {
         if bytes.len() == 16 {
             let mut b: [u8; 16] = [0; 16];
             b.copy_from_slice(bytes);
-            Ok(Self(u128::from_ne_bytes(b)))
+            Ok(Self(u128::from_le_bytes(b)))
         } else {
             Err(DeserializationError::InvalidByteLength(16, bytes.len()))
         }
@@ -100,7 +111,7 @@ impl ToSql<Binary, Pg> for Block {
         &self,
         out: &mut diesel::serialize::Output<W, Pg>,
     ) -> diesel::serialize::Result {
-        let bytes: [u8; 16] = self.0.to_ne_bytes();
+        let bytes: [u8; 16] = self.0.to_le_bytes();
         out.write(&bytes).map(|_| IsNull::No).map_err(Into::into)
     }
 }
@@ -120,6 +131,15 @@ pub struct NewBundle {
     pub block_height: Block,
 }
 
+/// Last bundle validated, validation resumes after `cursor`
+#[derive(Debug, PartialEq, Queryable)]
+pub struct ValidationCheckpoint {
+    pub id: i16,
+    pub cursor: String,
+    pub block: Block,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
 #[derive(Debug, PartialEq, Serialize, Queryable)]
 pub struct Transaction {
     pub id: String,
@@ -129,6 +149,15 @@ pub struct Transaction {
     pub signature: Vec<u8>,
     pub validated: bool,
     pub bundle_id: Option<String>,
+    pub size: Option<i64>,
+    pub fee: Option<String>,
+    pub currency: Option<String>,
+    pub bundler_signature: Option<Vec<u8>>,
+    pub late: bool,
+    pub missing: bool,
+    /// `block_promised` as a number, kept by the database
+    pub block_promised_height: i64,
+    pub resolved: bool,
 }
 
 #[derive(Insertable, Clone, AsChangeset)]
@@ -141,6 +170,174 @@ pub struct NewTransaction {
     pub signature: Vec<u8>,
     pub validated: bool,
     pub bundle_id: Option<String>,
+    pub size: Option<i64>,
+    pub fee: Option<String>,
+    pub currency: Option<String>,
+    pub bundler_signature: Option<Vec<u8>>,
+}
+
+/// Conflicting bundler promise for an already cosigned transaction
+///
+/// `original_signature` is the receipt issued for the first promise and
+/// `original_*` fields hold the first promise when it was stored. The rest is
+/// the second, bundler signed, promise for the same transaction id.
+#[derive(Debug, PartialEq, Serialize, Queryable)]
+pub struct Equivocation {
+    pub id: i32,
+    pub tx_id: String,
+    pub original_signature: Vec<u8>,
+    pub size: i64,
+    pub fee: String,
+    pub currency: String,
+    pub block_promised: Block,
+    pub bundler_signature: Vec<u8>,
+    pub created_at: chrono::NaiveDateTime,
+    pub original_size: Option<i64>,
+    pub original_fee: Option<String>,
+    pub original_currency: Option<String>,
+    pub original_block_promised: Option<Block>,
+    pub original_bundler_signature: Option<Vec<u8>>,
+}
+
+#[derive(Insertable, Clone)]
+#[table_name = "equivocations"]
+pub struct NewEquivocation {
+    pub tx_id: String,
+    pub original_signature: Vec<u8>,
+    pub size: i64,
+    pub fee: String,
+    pub currency: String,
+    pub block_promised: Block,
+    pub bundler_signature: Vec<u8>,
+    pub original_size: Option<i64>,
+    pub original_fee: Option<String>,
+    pub original_currency: Option<String>,
+    pub original_block_promised: Option<Block>,
+    pub original_bundler_signature: Option<Vec<u8>>,
+}
+
+/// Entry of the hash-chained log of issued validator signatures
+///
+/// `hash` covers every other field, `prev_hash` is the hash of the entry with
+/// the previous `seq`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Queryable, Insertable)]
+#[table_name = "audit_log"]
+pub struct AuditLogEntry {
+    pub seq: i64,
+    pub tx_id: String,
+    pub request: String,
+    pub signature: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// Last known validator state, the table holds a single row with `id` 1
+#[derive(Debug, PartialEq, Queryable, Insertable, AsChangeset)]
+#[table_name = "validator_state"]
+pub struct PersistedState {
+    pub id: i16,
+    pub current_block: Block,
+    pub current_epoch: Epoch,
+    pub role: i16,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable)]
+#[table_name = "scheduled_epochs"]
+pub struct PersistedEpoch {
+    pub seq: Epoch,
+    pub height: Block,
+    pub role: i16,
+}
+
+/// Receipt another validator issued and forwarded to the leader
+#[derive(Clone, Debug, PartialEq, Insertable)]
+#[table_name = "receipts"]
+pub struct NewReceipt {
+    pub tx_id: String,
+    pub validator: String,
+    pub epoch: Epoch,
+    pub block_promised: Block,
+    pub size: i64,
+    pub fee: String,
+    pub currency: String,
+    pub bundler_signature: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct StoredReceipt {
+    pub tx_id: String,
+    pub validator: String,
+    pub epoch: Epoch,
+    pub block_promised: Block,
+    pub size: i64,
+    pub fee: String,
+    pub currency: String,
+    pub bundler_signature: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub received_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, PartialEq, Serialize, Queryable, Insertable)]
+#[table_name = "receipt_aggregates"]
+pub struct ReceiptAggregate {
+    pub epoch: Epoch,
+    pub tx_id: String,
+    pub block_promised: Block,
+    pub cosigners: i32,
+    pub conflicting: bool,
+}
+
+/// Summary of the receipts collected while leading an epoch
+#[derive(Debug, PartialEq, Serialize, Queryable, Insertable)]
+#[table_name = "leader_reports"]
+pub struct LeaderReport {
+    pub epoch: Epoch,
+    pub tx_count: i64,
+    pub receipt_count: i64,
+    pub conflicting_count: i64,
+    pub participants: Vec<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// Slash proposal as serialized JSON, `submitted_at` is set once the
+/// contract gateway accepted it
+#[derive(Debug, PartialEq, Queryable)]
+pub struct StoredSlashProposal {
+    pub tx_id: String,
+    pub proposal: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub submitted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[table_name = "slash_proposals"]
+pub struct NewSlashProposal {
+    pub tx_id: String,
+    pub proposal: String,
+}
+
+/// Bundler key and the blocks it was in use at
+#[derive(Debug, PartialEq, Queryable)]
+pub struct StoredBundlerKey {
+    pub id: i64,
+    pub address: String,
+    pub key_type: String,
+    pub key: Vec<u8>,
+    pub valid_from: Block,
+    pub valid_until: Option<Block>,
+}
+
+#[derive(Insertable)]
+#[table_name = "bundler_key_history"]
+pub struct NewBundlerKey {
+    pub address: String,
+    pub key_type: String,
+    pub key: Vec<u8>,
+    pub valid_from: Block,
+    pub valid_until: Option<Block>,
 }
 
 #[cfg(test)]
@@ -168,6 +365,10 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
                 },
                 NewTransaction {
                     id: "2222222222222222222222222222222222222222222".to_string(),
@@ -177,6 +378,10 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
                 },
                 NewTransaction {
                     id: "3333333333333333333333333333333333333333333".to_string(),
@@ -186,6 +391,10 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
                 },
             ]
             .iter()
@@ -212,6 +421,10 @@ mod tests {
             signature: "foo".as_bytes().to_vec(),
             validated: false,
             bundle_id: None,
+            size: Some(1),
+            fee: Some("2".to_string()),
+            currency: Some("arweave".to_string()),
+            bundler_signature: Some("bar".as_bytes().to_vec()),
         };
 
         diesel::insert_into(dsl::transactions)
@@ -234,6 +447,14 @@ mod tests {
                 signature: "foo".as_bytes().to_vec(),
                 validated: false,
                 bundle_id: None,
+                size: Some(1),
+                fee: Some("2".to_string()),
+                currency: Some("arweave".to_string()),
+                bundler_signature: Some("bar".as_bytes().to_vec()),
+                late: false,
+                missing: false,
+                block_promised_height: 20,
+                resolved: false,
             }
         )
     }
@@ -260,6 +481,14 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
+                    late: false,
+                    missing: false,
+                    block_promised_height: 10,
+                    resolved: false,
                 },
                 Transaction {
                     id: "3333333333333333333333333333333333333333333".to_string(),
@@ -269,6 +498,14 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
+                    late: false,
+                    missing: false,
+                    block_promised_height: 10,
+                    resolved: false,
                 }
             ]
         )
@@ -296,6 +533,14 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
+                    late: false,
+                    missing: false,
+                    block_promised_height: 10,
+                    resolved: false,
                 },
                 Transaction {
                     id: "3333333333333333333333333333333333333333333".to_string(),
@@ -305,6 +550,14 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
+                    late: false,
+                    missing: false,
+                    block_promised_height: 10,
+                    resolved: false,
                 },
                 Transaction {
                     id: "2222222222222222222222222222222222222222222".to_string(),
@@ -314,6 +567,14 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: None,
+                    fee: None,
+                    currency: None,
+                    bundler_signature: None,
+                    late: false,
+                    missing: false,
+                    block_promised_height: 20,
+                    resolved: false,
                 },
                 Transaction {
                     id: "4444444444444444444444444444444444444444444".to_string(),
@@ -323,6 +584,14 @@ mod tests {
                     signature: "foo".as_bytes().to_vec(),
                     validated: false,
                     bundle_id: None,
+                    size: Some(1),
+                    fee: Some("2".to_string()),
+                    currency: Some("arweave".to_string()),
+                    bundler_signature: Some("bar".as_bytes().to_vec()),
+                    late: false,
+                    missing: false,
+                    block_promised_height: 20,
+                    resolved: false,
                 },
             ]
         )
@@ -4,10 +4,12 @@ use diesel::result::Error;
 use diesel::QueryDsl;
 use log::error;
 extern crate diesel;
-use crate::database::models::{Bundle, NewBundle, NewTransaction, Transaction};
+use crate::database::models::{
+    Block, Bundle, NewBundle, NewTransaction, Transaction, ValidationCheckpoint,
+};
 use crate::database::schema::bundle::dsl::*;
 use crate::database::schema::transactions::dsl::*;
-use crate::database::schema::{bundle, transactions};
+use crate::database::schema::{bundle, bundle_validation, transactions};
 use crate::state::ValidatorStateAccess;
 
 use super::models::Epoch;
@@ -40,6 +42,49 @@ where
     Ok(())
 }
 
+pub fn get_validation_checkpoint<Context>(
+    ctx: &Context,
+) -> Result<Option<ValidationCheckpoint>, Error>
+where
+    Context: QueryContext,
+{
+    let conn = ctx.get_db_connection();
+    bundle_validation::table
+        .find(1i16)
+        .first::<ValidationCheckpoint>(&conn)
+        .optional()
+}
+
+/// Record `cursor` of the last validated bundle, included in `block`
+pub fn save_validation_checkpoint<Context>(
+    ctx: &Context,
+    cursor: &str,
+    block: u128,
+) -> Result<(), Error>
+where
+    Context: QueryContext,
+{
+    let conn = ctx.get_db_connection();
+    let now = chrono::Utc::now().naive_utc();
+    diesel::insert_into(bundle_validation::table)
+        .values((
+            bundle_validation::id.eq(1i16),
+            bundle_validation::cursor.eq(cursor),
+            bundle_validation::block.eq(Block(block)),
+            bundle_validation::updated_at.eq(now),
+        ))
+        .on_conflict(bundle_validation::id)
+        .do_update()
+        .set((
+            bundle_validation::cursor.eq(cursor),
+            bundle_validation::block.eq(Block(block)),
+            bundle_validation::updated_at.eq(now),
+        ))
+        .execute(&conn)?;
+
+    Ok(())
+}
+
 pub fn insert_tx_in_db<Context>(ctx: &Context, new_tx: &NewTransaction) -> Result<(), Error>
 where
     Context: QueryContext,
@@ -88,11 +133,17 @@ where
     Context: QueryContext,
 {
     let conn = ctx.get_db_connection();
-    let last_epoch = Epoch(current_epoch - epoch_amount);
-    // TODO: Transactions cleared should not be the ones who caused slashing
+    let last_epoch = Epoch(current_epoch.saturating_sub(epoch_amount));
+    // Late or missing transactions are kept until a slash proposal is recorded for them
     let txs = transactions
         .filter(transactions::epoch.lt(last_epoch))
-        .filter(transactions::validated.eq(true));
+        .filter(
+            transactions::validated
+                .eq(true)
+                .and(transactions::late.eq(false))
+                .and(transactions::missing.eq(false))
+                .or(crate::slashing::has_proposal()),
+        );
     diesel::delete(txs)
         .execute(&conn)
 }
@@ -1,3 +1,15 @@
+table! {
+    audit_log (seq) {
+        seq -> Int8,
+        tx_id -> Bpchar,
+        request -> Text,
+        signature -> Text,
+        created_at -> Timestamp,
+        prev_hash -> Bpchar,
+        hash -> Bpchar,
+    }
+}
+
 table! {
     bundle (id) {
         id -> Bpchar,
@@ -6,12 +18,119 @@ table! {
     }
 }
 
+table! {
+    bundle_validation (id) {
+        id -> Int2,
+        cursor -> Text,
+        block -> Bytea,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    bundler_key_history (id) {
+        id -> Int8,
+        address -> Varchar,
+        key_type -> Varchar,
+        key -> Bytea,
+        valid_from -> Bytea,
+        valid_until -> Nullable<Bytea>,
+    }
+}
+
+table! {
+    epoch_reports (epoch) {
+        epoch -> Bytea,
+        report -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    equivocations (id) {
+        id -> Int4,
+        tx_id -> Bpchar,
+        original_signature -> Bytea,
+        size -> Int8,
+        fee -> Varchar,
+        currency -> Varchar,
+        block_promised -> Bytea,
+        bundler_signature -> Bytea,
+        created_at -> Timestamp,
+        original_size -> Nullable<Int8>,
+        original_fee -> Nullable<Varchar>,
+        original_currency -> Nullable<Varchar>,
+        original_block_promised -> Nullable<Bytea>,
+        original_bundler_signature -> Nullable<Bytea>,
+    }
+}
+
+table! {
+    leader_reports (epoch) {
+        epoch -> Bytea,
+        tx_count -> Int8,
+        receipt_count -> Int8,
+        conflicting_count -> Int8,
+        participants -> Array<Text>,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     leaders (address) {
         address -> Bpchar,
     }
 }
 
+table! {
+    receipt_aggregates (epoch, tx_id) {
+        epoch -> Bytea,
+        tx_id -> Bpchar,
+        block_promised -> Bytea,
+        cosigners -> Int4,
+        conflicting -> Bool,
+    }
+}
+
+table! {
+    receipt_forwarding (id) {
+        id -> Int2,
+        last_seq -> Int8,
+    }
+}
+
+table! {
+    receipts (tx_id, validator) {
+        tx_id -> Bpchar,
+        validator -> Bpchar,
+        epoch -> Bytea,
+        block_promised -> Bytea,
+        size -> Int8,
+        fee -> Varchar,
+        currency -> Varchar,
+        bundler_signature -> Bytea,
+        signature -> Bytea,
+        received_at -> Timestamp,
+    }
+}
+
+table! {
+    scheduled_epochs (seq) {
+        seq -> Bytea,
+        height -> Bytea,
+        role -> Int2,
+    }
+}
+
+table! {
+    slash_proposals (tx_id) {
+        tx_id -> Bpchar,
+        proposal -> Text,
+        created_at -> Timestamp,
+        submitted_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     transactions (id) {
         id -> Bpchar,
@@ -21,6 +140,14 @@ table! {
         signature -> Bytea,
         validated -> Bool,
         bundle_id -> Nullable<Bpchar>,
+        size -> Nullable<Int8>,
+        fee -> Nullable<Varchar>,
+        currency -> Nullable<Varchar>,
+        bundler_signature -> Nullable<Bytea>,
+        late -> Bool,
+        missing -> Bool,
+        block_promised_height -> Int8,
+        resolved -> Bool,
     }
 }
 
@@ -31,7 +158,34 @@ table! {
     }
 }
 
+table! {
+    validator_state (id) {
+        id -> Int2,
+        current_block -> Bytea,
+        current_epoch -> Bytea,
+        role -> Int2,
+        updated_at -> Timestamp,
+    }
+}
+
 joinable!(leaders -> validators (address));
 joinable!(transactions -> bundle (bundle_id));
 
-allow_tables_to_appear_in_same_query!(bundle, leaders, transactions, validators,);
+allow_tables_to_appear_in_same_query!(
+    audit_log,
+    bundle,
+    bundle_validation,
+    bundler_key_history,
+    epoch_reports,
+    equivocations,
+    leader_reports,
+    leaders,
+    receipt_aggregates,
+    receipt_forwarding,
+    receipts,
+    scheduled_epochs,
+    slash_proposals,
+    transactions,
+    validator_state,
+    validators,
+);
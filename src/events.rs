@@ -0,0 +1,117 @@
+//! Broadcast of validator state transitions
+//!
+//! Crons and routes publish what they changed, anything interested in those
+//! changes subscribes instead of polling [`crate::state::State`]. Events are
+//! dropped when nobody listens, slow subscribers miss the oldest events.
+
+use bundlr_contracts_validators::slashing::Proposal as SlashProposal;
+use log::{info, warn};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::state::ValidatorRole;
+
+/// Events kept for subscribers that fall behind
+pub const EVENT_CAPACITY: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub enum ValidatorEvent {
+    /// Network moved on to a new block height
+    NewBlock { height: u128 },
+    /// Epoch announced by the contract became active
+    EpochChanged { seq: u128, height: u128 },
+    /// Role changed with the epoch
+    RoleChanged {
+        epoch: u128,
+        previous: ValidatorRole,
+        role: ValidatorRole,
+    },
+    /// Slash proposal this validator has not voted on yet
+    NewSlashProposal(SlashProposal),
+    /// Validator signature was issued and stored
+    SignatureIssued {
+        tx_id: String,
+        validator: String,
+        epoch: u128,
+    },
+}
+
+pub trait EventsAccess {
+    fn events(&self) -> &EventBus;
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ValidatorEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: ValidatorEvent) {
+        // Fails only when there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ValidatorEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(EVENT_CAPACITY)
+    }
+}
+
+/// Log every state transition
+pub async fn log_events(mut events: broadcast::Receiver<ValidatorEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(ValidatorEvent::SignatureIssued { .. }) => (),
+            Ok(event) => info!("Validator event: {:?}", event),
+            Err(RecvError::Lagged(missed)) => warn!("Event log missed {} events", missed),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::ValidatorRole;
+
+    use super::{EventBus, ValidatorEvent};
+
+    #[actix_rt::test]
+    async fn every_subscriber_receives_published_events() {
+        let events = EventBus::default();
+        // Publishing without subscribers is fine
+        events.publish(ValidatorEvent::NewBlock { height: 1 });
+
+        let mut first = events.subscribe();
+        let mut second = events.subscribe();
+        events.publish(ValidatorEvent::NewBlock { height: 2 });
+        events.publish(ValidatorEvent::RoleChanged {
+            epoch: 3,
+            previous: ValidatorRole::Cosigner,
+            role: ValidatorRole::Idle,
+        });
+
+        for subscriber in [&mut first, &mut second] {
+            assert!(matches!(
+                subscriber.recv().await.unwrap(),
+                ValidatorEvent::NewBlock { height: 2 }
+            ));
+            assert!(matches!(
+                subscriber.recv().await.unwrap(),
+                ValidatorEvent::RoleChanged {
+                    epoch: 3,
+                    role: ValidatorRole::Idle,
+                    ..
+                }
+            ));
+        }
+    }
+}
@@ -1,26 +1,46 @@
-use std::ops::Deref;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+pub mod remote;
+
+use std::{io, ops::Deref};
 
 use data_encoding::BASE64URL_NOPAD;
 use jsonwebkey::JsonWebKey;
 use openssl::{
+    bn::BigNum,
+    error::ErrorStack,
     hash::MessageDigest,
     pkey::{PKey, Private, Public},
-    rsa::Padding,
+    rsa::{Padding, Rsa},
     sha::Sha256,
     sign,
 };
+use thiserror::Error;
+
+use crate::bundler_keys::BundlerKeys;
+
+#[derive(Debug, Error)]
+pub enum KeyManagerError {
+    #[error("crypto operation failed: {0}")]
+    Crypto(#[from] ErrorStack),
+    #[error("signer is unavailable: {0}")]
+    SignerUnavailable(#[from] io::Error),
+    #[error("signer failed: {0}")]
+    Signer(String),
+}
 
 pub trait KeyManagerAccess<KeyManager>
 where
     KeyManager: self::KeyManager,
 {
-    fn get_key_manager(&self) -> &KeyManager;
+    fn get_key_manager(&self) -> KeyManager;
 }
 
 pub trait KeyManager {
     fn bundler_address(&self) -> &str; // FIXME: replace with Address
     fn validator_address(&self) -> &str; // FIXME: replace with Address
-    fn validator_sign(&self, data: &[u8]) -> Vec<u8>;
+    fn validator_public_key(&self) -> &PKey<Public>;
+    fn validator_sign(&self, data: &[u8]) -> Result<Vec<u8>, KeyManagerError>;
     // FIXME: return Result
     fn verify_bundler_signature(&self, data: &[u8], sig: &[u8]) -> bool;
     // FIXME: return Result
@@ -40,7 +60,11 @@ where
         self.deref().validator_address()
     }
 
-    fn validator_sign(&self, data: &[u8]) -> Vec<u8> {
+    fn validator_public_key(&self) -> &PKey<Public> {
+        self.deref().validator_public_key()
+    }
+
+    fn validator_sign(&self, data: &[u8]) -> Result<Vec<u8>, KeyManagerError> {
         self.deref().validator_sign(data)
     }
 
@@ -53,6 +77,39 @@ where
     }
 }
 
+/// Sign with RSA-PSS over SHA-256, as used for validator signatures
+pub fn rsa_pss_sign(key: &PKey<Private>, data: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let mut signer = sign::Signer::new(MessageDigest::sha256(), key)?;
+    signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+    signer.update(data)?;
+    signer.sign_to_vec()
+}
+
+pub(crate) fn rsa_pss_verify(key: &PKey<Public>, data: &[u8], sig: &[u8]) -> bool {
+    let mut verifier = sign::Verifier::new(MessageDigest::sha256(), key).unwrap();
+    verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+    verifier.update(data).unwrap();
+    // TODO: we shouldn't probably hide errors here, at least we should log them
+    verifier.verify(sig).unwrap_or(false)
+}
+
+/// Public exponent of every Arweave key
+const RSA_EXPONENT: u32 = 65537;
+
+/// Arweave public key with the given RSA modulus
+pub fn public_key_from_modulus(n: &[u8]) -> Result<PKey<Public>, ErrorStack> {
+    let rsa =
+        Rsa::from_public_components(BigNum::from_slice(n)?, BigNum::from_u32(RSA_EXPONENT)?)?;
+    PKey::from_rsa(rsa)
+}
+
+/// Arweave address is the hash of the RSA modulus
+pub fn address_from_modulus(n: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(n);
+    BASE64URL_NOPAD.encode(&hasher.finish())
+}
+
 pub fn split_jwk(jwk: &JsonWebKey) -> (PKey<Private>, PKey<Public>, String) {
     let priv_key = {
         let der = jwk.key.try_to_der().unwrap();
@@ -91,8 +148,7 @@ pub trait InMemoryKeyManagerConfig {
 }
 
 pub struct InMemoryKeyManager {
-    bundler_address: String,
-    bundler_public: PKey<Public>,
+    bundler_keys: BundlerKeys,
     validator_address: String,
     validator_public: PKey<Public>,
     validator_private: PKey<Private>,
@@ -103,15 +159,18 @@ impl InMemoryKeyManager {
     where
         Config: InMemoryKeyManagerConfig,
     {
-        let bundler_jwk = config.bundler_jwk();
-        let validator_jwk = config.validator_jwk();
+        Self::with_bundler_keys(
+            BundlerKeys::new(config.bundler_jwk()),
+            config.validator_jwk(),
+        )
+    }
 
-        let (bundler_public, bundler_address) = split_public_only_jwk(bundler_jwk);
+    /// Key manager sharing the set of accepted bundler keys with others
+    pub fn with_bundler_keys(bundler_keys: BundlerKeys, validator_jwk: &JsonWebKey) -> Self {
         let (validator_private, validator_public, validator_address) = split_jwk(validator_jwk);
 
         Self {
-            bundler_address,
-            bundler_public,
+            bundler_keys,
             validator_address,
             validator_private,
             validator_public,
@@ -121,40 +180,27 @@ impl InMemoryKeyManager {
 
 impl KeyManager for InMemoryKeyManager {
     fn bundler_address(&self) -> &str {
-        &self.bundler_address
+        self.bundler_keys.address()
     }
 
     fn validator_address(&self) -> &str {
         &self.validator_address
     }
 
-    // TODO: should this return Result?
-    // When returning Result, caller can decide what needs to be done if
-    // this call fails, instead of panicking here.
-    fn validator_sign(&self, data: &[u8]) -> Vec<u8> {
-        let mut signer =
-            sign::Signer::new(MessageDigest::sha256(), &self.validator_private).unwrap();
-        signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
-        signer.update(data).unwrap();
-        signer.sign_to_vec().unwrap()
+    fn validator_public_key(&self) -> &PKey<Public> {
+        &self.validator_public
+    }
+
+    fn validator_sign(&self, data: &[u8]) -> Result<Vec<u8>, KeyManagerError> {
+        Ok(rsa_pss_sign(&self.validator_private, data)?)
     }
 
     fn verify_bundler_signature(&self, data: &[u8], sig: &[u8]) -> bool {
-        let mut verifier =
-            sign::Verifier::new(MessageDigest::sha256(), &self.bundler_public).unwrap();
-        verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
-        verifier.update(data).unwrap();
-        // TODO: we shouldn't probably hide errors here, at least we should log them
-        verifier.verify(sig).unwrap_or(false)
+        self.bundler_keys.verify(data, sig)
     }
 
     fn verify_validator_signature(&self, data: &[u8], sig: &[u8]) -> bool {
-        let mut verifier =
-            sign::Verifier::new(MessageDigest::sha256(), &self.validator_public).unwrap();
-        verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
-        verifier.update(data).unwrap();
-        // TODO: we shouldn't probably hide errors here, at least we should log them
-        verifier.verify(sig).unwrap_or(false)
+        rsa_pss_verify(&self.validator_public, data, sig)
     }
 }
 
@@ -166,23 +212,15 @@ pub mod test_utils {
     use openssl::rsa::Rsa;
     use openssl::sha::Sha256;
 
-    use super::{split_jwk, split_public_only_jwk, InMemoryKeyManager};
+    use super::InMemoryKeyManager;
+    use crate::bundler_keys::BundlerKeys;
 
     pub fn test_keys() -> (InMemoryKeyManager, PKey<Private>) {
         let (bundler_jwk, bundler_private) = bundler_key();
         let validator_jwk = validator_key();
 
-        let (bundler_public, bundler_address) = split_public_only_jwk(&bundler_jwk);
-        let (validator_private, validator_public, validator_address) = split_jwk(&validator_jwk);
-
         (
-            InMemoryKeyManager {
-                bundler_address,
-                bundler_public,
-                validator_address,
-                validator_private,
-                validator_public,
-            },
+            InMemoryKeyManager::with_bundler_keys(BundlerKeys::new(&bundler_jwk), &validator_jwk),
             bundler_private,
         )
     }
@@ -0,0 +1,243 @@
+//! Key manager signing with a validator key held by a PKCS#11 token
+//!
+//! Private key never leaves the token, it is looked up by its label and used
+//! for RSA-PSS over SHA-256 signatures. Works with any PKCS#11 module, e.g.
+//! SoftHSM for local testing.
+
+use std::{path::Path, sync::Mutex};
+
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11},
+    error::{Error as Pkcs11Error, RvError},
+    mechanism::{
+        rsa::{PkcsMgfType, PkcsPssParams},
+        Mechanism, MechanismType,
+    },
+    object::{Attribute, AttributeType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+    slot::Slot,
+};
+use openssl::{
+    bn::BigNum,
+    pkey::{PKey, Public},
+    rsa::Rsa,
+};
+
+use super::{address_from_modulus, rsa_pss_verify, KeyManager, KeyManagerError};
+use crate::bundler_keys::BundlerKeys;
+
+/// Salt length used by Arweave for RSA-PSS signatures
+const PSS_SALT_LEN: u64 = 32;
+
+impl From<Pkcs11Error> for KeyManagerError {
+    fn from(err: Pkcs11Error) -> Self {
+        KeyManagerError::Signer(format!("PKCS#11: {}", err))
+    }
+}
+
+pub struct Pkcs11Config {
+    pub slot: u64,
+    pub pin: String,
+    pub key_label: String,
+}
+
+/// Load and initialize PKCS#11 module
+///
+/// Module can be initialized only once per process, the returned context is
+/// meant to be shared by all key managers.
+pub fn load_module(path: impl AsRef<Path>) -> Result<Pkcs11, KeyManagerError> {
+    let pkcs11 = Pkcs11::new(path)?;
+    pkcs11.initialize(CInitializeArgs::OsThreads)?;
+    Ok(pkcs11)
+}
+
+fn find_slot(pkcs11: &Pkcs11, slot_id: u64) -> Result<Slot, KeyManagerError> {
+    pkcs11
+        .get_slots_with_token()?
+        .into_iter()
+        .find(|slot| slot.id() == slot_id)
+        .ok_or_else(|| KeyManagerError::Signer(format!("no token in slot {}", slot_id)))
+}
+
+/// Log in as user, login state is shared by all sessions of the process
+pub fn login(session: &Session, pin: &str) -> Result<(), KeyManagerError> {
+    match session.login(UserType::User, Some(pin)) {
+        Ok(()) | Err(Pkcs11Error::Pkcs11(RvError::UserAlreadyLoggedIn)) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub struct Pkcs11KeyManager {
+    session: Mutex<Session>,
+    key: ObjectHandle,
+    bundler_keys: BundlerKeys,
+    validator_address: String,
+    validator_public: PKey<Public>,
+}
+
+impl Pkcs11KeyManager {
+    /// Open a session on the configured slot and look up the validator key
+    pub fn open(
+        pkcs11: &Pkcs11,
+        config: &Pkcs11Config,
+        bundler_keys: BundlerKeys,
+    ) -> Result<Self, KeyManagerError> {
+        let slot = find_slot(pkcs11, config.slot)?;
+        let session = pkcs11.open_ro_session(slot)?;
+        login(&session, &config.pin)?;
+
+        let label = config.key_label.as_bytes().to_vec();
+        let key = match session.find_objects(&[
+            Attribute::Class(ObjectClass::PRIVATE_KEY),
+            Attribute::Label(label),
+        ])?[..]
+        {
+            [key] => key,
+            [] => {
+                return Err(KeyManagerError::Signer(format!(
+                    "no private key labeled {}",
+                    config.key_label
+                )))
+            }
+            _ => {
+                return Err(KeyManagerError::Signer(format!(
+                    "more than one private key labeled {}",
+                    config.key_label
+                )))
+            }
+        };
+
+        let mut n = None;
+        let mut e = None;
+        for attribute in session.get_attributes(
+            key,
+            &[AttributeType::Modulus, AttributeType::PublicExponent],
+        )? {
+            match attribute {
+                Attribute::Modulus(value) => n = Some(value),
+                Attribute::PublicExponent(value) => e = Some(value),
+                _ => (),
+            }
+        }
+        let (n, e) = n.zip(e).ok_or_else(|| {
+            KeyManagerError::Signer("validator key is not an RSA key".to_string())
+        })?;
+
+        let rsa = Rsa::from_public_components(BigNum::from_slice(&n)?, BigNum::from_slice(&e)?)?;
+
+        Ok(Self {
+            session: Mutex::new(session),
+            key,
+            bundler_keys,
+            validator_address: address_from_modulus(&n),
+            validator_public: PKey::from_rsa(rsa)?,
+        })
+    }
+}
+
+impl KeyManager for Pkcs11KeyManager {
+    fn bundler_address(&self) -> &str {
+        self.bundler_keys.address()
+    }
+
+    fn validator_address(&self) -> &str {
+        &self.validator_address
+    }
+
+    fn validator_public_key(&self) -> &PKey<Public> {
+        &self.validator_public
+    }
+
+    fn validator_sign(&self, data: &[u8]) -> Result<Vec<u8>, KeyManagerError> {
+        let mechanism = Mechanism::Sha256RsaPkcsPss(PkcsPssParams {
+            hash_alg: MechanismType::SHA256,
+            mgf: PkcsMgfType::MGF1_SHA256,
+            s_len: PSS_SALT_LEN.into(),
+        });
+
+        // PKCS#11 sessions can't be used from more than one thread at a time
+        let session = self.session.lock().unwrap();
+        Ok(session.sign(&mechanism, self.key, data)?)
+    }
+
+    fn verify_bundler_signature(&self, data: &[u8], sig: &[u8]) -> bool {
+        self.bundler_keys.verify(data, sig)
+    }
+
+    fn verify_validator_signature(&self, data: &[u8], sig: &[u8]) -> bool {
+        rsa_pss_verify(&self.validator_public, data, sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cryptoki::{
+        mechanism::Mechanism,
+        object::{Attribute, ObjectClass},
+    };
+
+    use crate::{
+        bundler_keys::BundlerKeys,
+        key_manager::{test_utils::bundler_key, KeyManager},
+    };
+
+    use super::{find_slot, load_module, login, Pkcs11Config, Pkcs11KeyManager};
+
+    fn env(name: &str) -> String {
+        std::env::var(name).unwrap_or_else(|_| panic!("{} must be set", name))
+    }
+
+    // Needs an initialized token, e.g. SoftHSM, see README
+    #[test]
+    #[ignore = "requires PKCS#11 token"]
+    fn sign_with_key_on_token() {
+        let pkcs11 = load_module(env("PKCS11_MODULE")).unwrap();
+        let config = Pkcs11Config {
+            slot: env("PKCS11_SLOT").parse().unwrap(),
+            pin: env("PKCS11_PIN"),
+            key_label: format!("validator-test-{}", std::process::id()),
+        };
+
+        let slot = find_slot(&pkcs11, config.slot).unwrap();
+        let session = pkcs11.open_rw_session(slot).unwrap();
+        login(&session, &config.pin).unwrap();
+        let label = config.key_label.as_bytes().to_vec();
+        let (public, private) = session
+            .generate_key_pair(
+                &Mechanism::RsaPkcsKeyPairGen,
+                &[
+                    Attribute::Token(true),
+                    Attribute::Verify(true),
+                    Attribute::PublicExponent(vec![1, 0, 1]),
+                    Attribute::ModulusBits(2048.into()),
+                    Attribute::Label(label.clone()),
+                ],
+                &[
+                    Attribute::Token(true),
+                    Attribute::Private(true),
+                    Attribute::Sensitive(true),
+                    Attribute::Sign(true),
+                    Attribute::Label(label.clone()),
+                ],
+            )
+            .unwrap();
+
+        let (bundler_jwk, _) = bundler_key();
+        let key_manager =
+            Pkcs11KeyManager::open(&pkcs11, &config, BundlerKeys::new(&bundler_jwk)).unwrap();
+        let sig = key_manager.validator_sign(b"hello, world!").unwrap();
+
+        session.destroy_object(public).unwrap();
+        session.destroy_object(private).unwrap();
+        assert!(session
+            .find_objects(&[
+                Attribute::Class(ObjectClass::PRIVATE_KEY),
+                Attribute::Label(label)
+            ])
+            .unwrap()
+            .is_empty());
+
+        assert!(key_manager.verify_validator_signature(b"hello, world!", &sig));
+        assert!(!key_manager.verify_validator_signature(b"hello, world?", &sig));
+    }
+}
@@ -0,0 +1,246 @@
+//! Key manager that keeps the validator private key out of the server process
+//!
+//! Signing is forwarded to a signer daemon over a Unix domain socket. Every
+//! message is a one byte tag followed by a big endian `u32` payload length and
+//! the payload. Requests are tagged with the operation, responses with
+//! [`STATUS_OK`] and the result or [`STATUS_ERROR`] and a UTF-8 message.
+
+use std::{
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use openssl::{
+    bn::BigNum,
+    pkey::{PKey, Private, Public},
+    rsa::Rsa,
+};
+
+use super::{address_from_modulus, rsa_pss_sign, rsa_pss_verify, KeyManager, KeyManagerError};
+use crate::bundler_keys::BundlerKeys;
+
+/// Get RSA modulus of the validator key, payload is empty
+pub const OP_PUBLIC_KEY: u8 = 1;
+/// Sign payload with the validator key
+pub const OP_SIGN: u8 = 2;
+
+pub const STATUS_OK: u8 = 0;
+pub const STATUS_ERROR: u8 = 1;
+
+const MAX_PAYLOAD_LEN: u32 = 1024 * 1024;
+
+pub fn write_frame<W: Write>(writer: &mut W, tag: u8, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len <= MAX_PAYLOAD_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "payload too large"))?;
+
+    let mut frame = Vec::with_capacity(5 + payload.len());
+    frame.push(tag);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header)?;
+
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+    if len > MAX_PAYLOAD_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "payload too large",
+        ));
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok((header[0], payload))
+}
+
+/// Serve requests coming over a single connection until the client hangs up
+pub fn serve_connection<S: Read + Write>(
+    stream: &mut S,
+    validator_private: &PKey<Private>,
+) -> io::Result<()> {
+    loop {
+        let (op, payload) = match read_frame(stream) {
+            Ok(frame) => frame,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let response = match op {
+            OP_PUBLIC_KEY => validator_private
+                .rsa()
+                .map(|rsa| rsa.n().to_vec())
+                .map_err(|err| err.to_string()),
+            OP_SIGN => rsa_pss_sign(validator_private, &payload).map_err(|err| err.to_string()),
+            other => Err(format!("unknown operation {}", other)),
+        };
+
+        match response {
+            Ok(payload) => write_frame(stream, STATUS_OK, &payload)?,
+            Err(message) => write_frame(stream, STATUS_ERROR, message.as_bytes())?,
+        }
+    }
+}
+
+fn request(
+    socket_path: &Path,
+    timeout: Duration,
+    op: u8,
+    payload: &[u8],
+) -> Result<Vec<u8>, KeyManagerError> {
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    write_frame(&mut stream, op, payload)?;
+    match read_frame(&mut stream)? {
+        (STATUS_OK, payload) => Ok(payload),
+        (STATUS_ERROR, message) => Err(KeyManagerError::Signer(
+            String::from_utf8_lossy(&message).into_owned(),
+        )),
+        (status, _) => Err(KeyManagerError::Signer(format!(
+            "unexpected response status {}",
+            status
+        ))),
+    }
+}
+
+pub struct RemoteKeyManager {
+    socket_path: PathBuf,
+    timeout: Duration,
+    bundler_keys: BundlerKeys,
+    validator_address: String,
+    validator_public: PKey<Public>,
+}
+
+impl RemoteKeyManager {
+    /// Ask the signer for the validator public key, fails when signer isn't running
+    pub fn connect(
+        socket_path: impl Into<PathBuf>,
+        timeout: Duration,
+        bundler_keys: BundlerKeys,
+    ) -> Result<Self, KeyManagerError> {
+        let socket_path = socket_path.into();
+
+        let n = request(&socket_path, timeout, OP_PUBLIC_KEY, &[])?;
+        let rsa = Rsa::from_public_components(BigNum::from_slice(&n)?, BigNum::from_u32(65537)?)?;
+        let validator_public = PKey::from_rsa(rsa)?;
+        let validator_address = address_from_modulus(&n);
+
+        Ok(Self {
+            socket_path,
+            timeout,
+            bundler_keys,
+            validator_address,
+            validator_public,
+        })
+    }
+}
+
+impl KeyManager for RemoteKeyManager {
+    fn bundler_address(&self) -> &str {
+        self.bundler_keys.address()
+    }
+
+    fn validator_address(&self) -> &str {
+        &self.validator_address
+    }
+
+    fn validator_public_key(&self) -> &PKey<Public> {
+        &self.validator_public
+    }
+
+    fn validator_sign(&self, data: &[u8]) -> Result<Vec<u8>, KeyManagerError> {
+        request(&self.socket_path, self.timeout, OP_SIGN, data)
+    }
+
+    fn verify_bundler_signature(&self, data: &[u8], sig: &[u8]) -> bool {
+        self.bundler_keys.verify(data, sig)
+    }
+
+    fn verify_validator_signature(&self, data: &[u8], sig: &[u8]) -> bool {
+        rsa_pss_verify(&self.validator_public, data, sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixListener, path::PathBuf, thread, time::Duration};
+
+    use crate::{
+        bundler_keys::BundlerKeys,
+        key_manager::{
+            split_jwk,
+            test_utils::{bundler_key, validator_key},
+            KeyManager, KeyManagerError,
+        },
+    };
+
+    use super::{read_frame, serve_connection, write_frame, RemoteKeyManager};
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "validator-signer-{}-{}.sock",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, 2, b"hello").unwrap();
+
+        let (tag, payload) = read_frame(&mut buf.as_slice()).unwrap();
+        assert_eq!(tag, 2);
+        assert_eq!(payload, b"hello");
+    }
+
+    #[test]
+    fn sign_through_signer_daemon() {
+        let path = socket_path("sign");
+        let (validator_private, _, validator_address) = split_jwk(&validator_key());
+
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                serve_connection(&mut stream.unwrap(), &validator_private).unwrap();
+            }
+        });
+
+        let (bundler_jwk, _) = bundler_key();
+        let key_manager = RemoteKeyManager::connect(
+            &path,
+            Duration::from_secs(5),
+            BundlerKeys::new(&bundler_jwk),
+        )
+        .unwrap();
+        assert_eq!(key_manager.validator_address(), validator_address);
+
+        let sig = key_manager.validator_sign(b"hello, world!").unwrap();
+        assert!(key_manager.verify_validator_signature(b"hello, world!", &sig));
+    }
+
+    #[test]
+    fn missing_signer_yields_unavailable_error() {
+        let path = socket_path("missing");
+        let (bundler_jwk, _) = bundler_key();
+
+        let res = RemoteKeyManager::connect(
+            &path,
+            Duration::from_secs(5),
+            BundlerKeys::new(&bundler_jwk),
+        );
+        assert!(matches!(res, Err(KeyManagerError::SignerUnavailable(_))));
+    }
+}
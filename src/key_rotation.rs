@@ -0,0 +1,286 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use bundlr_contracts_validators::Address;
+use log::{error, info};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    contract_gateway::{ContractGatewayAccess, ContractGatewayError},
+    http, key_manager,
+};
+
+#[derive(Debug, Error)]
+pub enum KeyRotationError {
+    #[error("failed to load key: {0}")]
+    Load(String),
+    #[error("failed to fetch contract state: {0}")]
+    ContractGateway(ContractGatewayError),
+    #[error("address {0} is not registered in the validators contract")]
+    NotRegistered(String),
+    #[error("key for address {0} is already in use")]
+    Unchanged(String),
+}
+
+/// Reply channel for a key reload request, receives the new validator address
+pub type ReloadReply = oneshot::Sender<Result<String, KeyRotationError>>;
+
+pub trait KeyRotationAccess<KeyManager> {
+    fn key_rotation(&self) -> &KeyRotation<KeyManager>;
+}
+
+struct Keys<KeyManager> {
+    current: Arc<KeyManager>,
+    /// Replaced key and the time until which it's still accepted
+    previous: Option<(Arc<KeyManager>, Instant)>,
+}
+
+/// Validator key manager that can be replaced at runtime
+///
+/// After a rotation requests addressed to the previous validator address are
+/// still signed with the previous key until the overlap period ends.
+pub struct KeyRotation<KeyManager> {
+    keys: RwLock<Keys<KeyManager>>,
+}
+
+impl<KeyManager> KeyRotation<KeyManager>
+where
+    KeyManager: key_manager::KeyManager,
+{
+    pub fn new(key_manager: KeyManager) -> Self {
+        Self {
+            keys: RwLock::new(Keys {
+                current: Arc::new(key_manager),
+                previous: None,
+            }),
+        }
+    }
+
+    pub fn current(&self) -> Arc<KeyManager> {
+        self.keys.read().unwrap().current.clone()
+    }
+
+    /// Key manager holding the key of `validator_address`, if it's still in use
+    pub fn for_address(&self, validator_address: &str) -> Option<Arc<KeyManager>> {
+        let keys = self.keys.read().unwrap();
+        if keys.current.validator_address() == validator_address {
+            return Some(keys.current.clone());
+        }
+
+        match &keys.previous {
+            Some((previous, until))
+                if Instant::now() < *until && previous.validator_address() == validator_address =>
+            {
+                Some(previous.clone())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn rotate(&self, key_manager: KeyManager, overlap: Duration) {
+        let mut keys = self.keys.write().unwrap();
+        let previous = std::mem::replace(&mut keys.current, Arc::new(key_manager));
+        keys.previous = Some((previous, Instant::now() + overlap));
+    }
+}
+
+/// Switch to a new validator key once its address is registered in the contract
+pub async fn rotate_validator_key<Context, HttpClient, KeyManager>(
+    ctx: &Context,
+    key_manager: KeyManager,
+    overlap: Duration,
+) -> Result<String, KeyRotationError>
+where
+    Context: ContractGatewayAccess + http::ClientAccess<HttpClient> + KeyRotationAccess<KeyManager>,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+    KeyManager: key_manager::KeyManager,
+{
+    let new_address = key_manager.validator_address().to_string();
+    if ctx.key_rotation().current().validator_address() == new_address {
+        return Err(KeyRotationError::Unchanged(new_address));
+    }
+
+    let state = ctx
+        .contract_gateway()
+        .get_current_state(ctx)
+        .await
+        .map_err(KeyRotationError::ContractGateway)?;
+
+    let registered = Address::from_str(&new_address)
+        .map(|address| state.validators.contains_key(&address))
+        .unwrap_or(false);
+    if !registered {
+        return Err(KeyRotationError::NotRegistered(new_address));
+    }
+
+    ctx.key_rotation().rotate(key_manager, overlap);
+    Ok(new_address)
+}
+
+/// Reload validator key every time a request comes in through `requests`
+pub async fn run_key_reloader<Context, HttpClient, KeyManager, Load>(
+    ctx: Context,
+    overlap: Duration,
+    load: Load,
+    mut requests: mpsc::Receiver<ReloadReply>,
+) where
+    Context: ContractGatewayAccess + http::ClientAccess<HttpClient> + KeyRotationAccess<KeyManager>,
+    HttpClient: http::Client<Request = reqwest::Request, Response = reqwest::Response>,
+    KeyManager: key_manager::KeyManager,
+    Load: Fn() -> Result<KeyManager, String>,
+{
+    while let Some(reply) = requests.recv().await {
+        let res = match load() {
+            Ok(key_manager) => rotate_validator_key(&ctx, key_manager, overlap).await,
+            Err(err) => Err(KeyRotationError::Load(err)),
+        };
+
+        match &res {
+            Ok(address) => info!(
+                "Validator key rotated to {}, previous key accepted for {:?}",
+                address, overlap
+            ),
+            Err(err) => error!("Validator key rotation failed: {}", err),
+        }
+
+        // Requester might not be waiting for the result anymore
+        let _ = reply.send(res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use bundlr_contracts_validators::{Address, Epoch, State, Validator};
+    use futures::executor::LocalPool;
+    use http::Method;
+
+    use crate::{
+        context::test_utils::test_context_with_http_client,
+        http::reqwest::mock::MockHttpClient,
+        key_manager::{test_utils::test_keys, KeyManager},
+    };
+
+    use super::{rotate_validator_key, KeyRotation, KeyRotationAccess, KeyRotationError};
+
+    fn contract_state(validators: Vec<Address>) -> State {
+        State {
+            bundler: "bundler_address".try_into().unwrap(),
+            bundlers_contract: "bundlers_contract_address".try_into().unwrap(),
+            epoch: Epoch {
+                seq: 1,
+                tx: "tx1".try_into().unwrap(),
+                height: 1,
+            },
+            epoch_duration: 2,
+            minimum_stake: 1.into(),
+            token: "token_contract_address".try_into().unwrap(),
+            max_num_nominated_validators: 10,
+            validators: validators
+                .into_iter()
+                .map(|address| {
+                    (
+                        address.clone(),
+                        Validator {
+                            address,
+                            url: "https://validator1.example.com".parse().unwrap(),
+                            stake: 1.into(),
+                        },
+                    )
+                })
+                .collect::<HashMap<_, _>>(),
+            nominated_validators: Vec::new(),
+            slash_proposal_lifetime: 10,
+            slash_proposals: HashMap::new(),
+        }
+    }
+
+    fn client_returning(state: State) -> MockHttpClient {
+        MockHttpClient::new(|a: &reqwest::Request, b: &reqwest::Request| a.url() == b.url())
+            .when(|req: &reqwest::Request| {
+                let url = "http://localhost:3000/validators/state";
+                req.method() == Method::GET && &req.url().to_string() == url
+            })
+            .then(move |_: &reqwest::Request| {
+                let body = serde_json::to_string(&state).unwrap();
+                http::response::Builder::new()
+                    .status(200)
+                    .body(body)
+                    .map(reqwest::Response::from)
+                    .unwrap()
+            })
+    }
+
+    #[test]
+    fn previous_key_is_accepted_only_during_overlap() {
+        let (old_keys, _) = test_keys();
+        let (new_keys, _) = test_keys();
+        let old_address = old_keys.validator_address().to_string();
+        let new_address = new_keys.validator_address().to_string();
+
+        let rotation = KeyRotation::new(old_keys);
+        rotation.rotate(new_keys, Duration::from_secs(60));
+        assert!(rotation.for_address(&old_address).is_some());
+        assert!(rotation.for_address(&new_address).is_some());
+        assert_eq!(rotation.current().validator_address(), new_address);
+
+        let (newest_keys, _) = test_keys();
+        rotation.rotate(newest_keys, Duration::ZERO);
+        assert!(rotation.for_address(&old_address).is_none());
+        assert!(rotation.for_address(&new_address).is_none());
+    }
+
+    #[test]
+    fn rotation_to_registered_address_is_accepted() {
+        let (key_manager, _) = test_keys();
+        let (new_keys, _) = test_keys();
+        let new_address = new_keys.validator_address().to_string();
+
+        let client = client_returning(contract_state(vec![new_address
+            .as_str()
+            .try_into()
+            .unwrap()]));
+        let ctx = test_context_with_http_client(key_manager, client);
+
+        let mut rt = LocalPool::new();
+        let res = rt.run_until(rotate_validator_key(
+            &ctx,
+            new_keys,
+            Duration::from_secs(60),
+        ));
+
+        assert_eq!(res.unwrap(), new_address);
+        assert_eq!(
+            ctx.key_rotation().current().validator_address(),
+            new_address
+        );
+    }
+
+    #[test]
+    fn rotation_to_unregistered_address_is_rejected() {
+        let (key_manager, _) = test_keys();
+        let old_address = key_manager.validator_address().to_string();
+        let (new_keys, _) = test_keys();
+
+        let client = client_returning(contract_state(Vec::new()));
+        let ctx = test_context_with_http_client(key_manager, client);
+
+        let mut rt = LocalPool::new();
+        let res = rt.run_until(rotate_validator_key(
+            &ctx,
+            new_keys,
+            Duration::from_secs(60),
+        ));
+
+        assert!(matches!(res, Err(KeyRotationError::NotRegistered(_))));
+        assert_eq!(
+            ctx.key_rotation().current().validator_address(),
+            old_address
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Password encrypted storage for the validator JWK
+//!
+//! Key for AES-256-GCM is derived from the password with scrypt, KDF
+//! parameters, IV and authentication tag are stored next to the ciphertext.
+
+use std::fs;
+
+use data_encoding::{DecodeError, BASE64URL_NOPAD};
+use jsonwebkey::JsonWebKey;
+use openssl::{
+    error::ErrorStack,
+    pkcs5::scrypt,
+    rand::rand_bytes,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const VERSION: u32 = 1;
+const CIPHER: &str = "aes-256-gcm";
+const KEY_LEN: usize = 32;
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const SALT_LEN: usize = 32;
+const AAD: &[u8] = b"validator-keystore-v1";
+
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    #[error("crypto operation failed: {0}")]
+    Crypto(#[from] ErrorStack),
+    #[error("wrong password or corrupted keystore")]
+    Decryption,
+    #[error("invalid keystore: {0}")]
+    InvalidFormat(String),
+    #[error("keystore is encrypted, password is required")]
+    PasswordRequired,
+    #[error("failed to read file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<DecodeError> for KeystoreError {
+    fn from(err: DecodeError) -> Self {
+        KeystoreError::InvalidFormat(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for KeystoreError {
+    fn from(err: serde_json::Error) -> Self {
+        KeystoreError::InvalidFormat(err.to_string())
+    }
+}
+
+/// scrypt cost parameters
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScryptParams {
+    pub n: u64,
+    pub r: u64,
+    pub p: u64,
+}
+
+impl Default for ScryptParams {
+    fn default() -> Self {
+        Self {
+            n: 1 << 17,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+impl ScryptParams {
+    fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN], ErrorStack> {
+        let mut key = [0u8; KEY_LEN];
+        // Memory needed by scrypt is 128 * r * N bytes, leave some headroom
+        let max_mem = 128 * self.r * self.n * self.p + 1024 * 1024;
+        scrypt(password, salt, self.n, self.r, self.p, max_mem, &mut key)?;
+        Ok(key)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub cipher: String,
+    pub kdf: ScryptParams,
+    pub salt: String,
+    pub iv: String,
+    pub tag: String,
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    pub fn encrypt(jwk: &JsonWebKey, password: &[u8]) -> Result<Self, KeystoreError> {
+        Self::encrypt_with_params(jwk, password, ScryptParams::default())
+    }
+
+    pub fn encrypt_with_params(
+        jwk: &JsonWebKey,
+        password: &[u8],
+        kdf: ScryptParams,
+    ) -> Result<Self, KeystoreError> {
+        let mut salt = [0u8; SALT_LEN];
+        rand_bytes(&mut salt)?;
+        let mut iv = [0u8; IV_LEN];
+        rand_bytes(&mut iv)?;
+
+        let key = kdf.derive_key(password, &salt)?;
+        let plaintext = serde_json::to_vec(jwk)?;
+        let mut tag = [0u8; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&iv),
+            AAD,
+            &plaintext,
+            &mut tag,
+        )?;
+
+        Ok(Self {
+            version: VERSION,
+            cipher: CIPHER.to_string(),
+            kdf,
+            salt: BASE64URL_NOPAD.encode(&salt),
+            iv: BASE64URL_NOPAD.encode(&iv),
+            tag: BASE64URL_NOPAD.encode(&tag),
+            ciphertext: BASE64URL_NOPAD.encode(&ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, password: &[u8]) -> Result<JsonWebKey, KeystoreError> {
+        if self.version != VERSION || self.cipher != CIPHER {
+            return Err(KeystoreError::InvalidFormat(format!(
+                "unsupported version {} with cipher {}",
+                self.version, self.cipher
+            )));
+        }
+
+        let salt = BASE64URL_NOPAD.decode(self.salt.as_bytes())?;
+        let iv = BASE64URL_NOPAD.decode(self.iv.as_bytes())?;
+        let tag = BASE64URL_NOPAD.decode(self.tag.as_bytes())?;
+        let ciphertext = BASE64URL_NOPAD.decode(self.ciphertext.as_bytes())?;
+
+        let key = self.kdf.derive_key(password, &salt)?;
+        let plaintext = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &key,
+            Some(&iv),
+            AAD,
+            &ciphertext,
+            &tag,
+        )
+        .map_err(|_| KeystoreError::Decryption)?;
+
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Re-encrypt with new password, salt and IV, keeping KDF parameters
+    pub fn change_password(
+        &self,
+        old_password: &[u8],
+        new_password: &[u8],
+    ) -> Result<Self, KeystoreError> {
+        let jwk = self.decrypt(old_password)?;
+        Self::encrypt_with_params(&jwk, new_password, self.kdf)
+    }
+}
+
+/// Read password from a file, trailing newline is not part of the password
+pub fn read_password_file(path: &str) -> Result<Vec<u8>, KeystoreError> {
+    let mut password = fs::read(path)?;
+    while matches!(password.last(), Some(b'\n') | Some(b'\r')) {
+        password.pop();
+    }
+    Ok(password)
+}
+
+/// Password from a file takes precedence over the one passed directly
+pub fn resolve_password(
+    file: Option<&str>,
+    value: Option<&str>,
+) -> Result<Option<Vec<u8>>, KeystoreError> {
+    match (file, value) {
+        (Some(path), _) => read_password_file(path).map(Some),
+        (None, Some(value)) => Ok(Some(value.as_bytes().to_vec())),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Parse key file holding either a plaintext JWK or an encrypted keystore
+pub fn parse_key(contents: &str, password: Option<&[u8]>) -> Result<JsonWebKey, KeystoreError> {
+    match serde_json::from_str::<Keystore>(contents) {
+        Ok(keystore) => keystore.decrypt(password.ok_or(KeystoreError::PasswordRequired)?),
+        Err(_) => contents
+            .parse()
+            .map_err(|err| KeystoreError::InvalidFormat(format!("{:?}", err))),
+    }
+}
+
+/// Load key file, see [`parse_key`]
+pub fn load_key(path: &str, password: Option<&[u8]>) -> Result<JsonWebKey, KeystoreError> {
+    parse_key(&fs::read_to_string(path)?, password)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key_manager::{split_jwk, test_utils::validator_key};
+
+    use super::{parse_key, Keystore, KeystoreError, ScryptParams};
+
+    // Keep tests fast, default parameters take a while
+    const TEST_PARAMS: ScryptParams = ScryptParams {
+        n: 1 << 10,
+        r: 8,
+        p: 1,
+    };
+
+    fn address(jwk: &jsonwebkey::JsonWebKey) -> String {
+        split_jwk(jwk).2
+    }
+
+    #[test]
+    fn decrypt_with_correct_password() {
+        let jwk = validator_key();
+        let keystore = Keystore::encrypt_with_params(&jwk, b"secret", TEST_PARAMS).unwrap();
+
+        let decrypted = keystore.decrypt(b"secret").unwrap();
+        assert_eq!(address(&decrypted), address(&jwk));
+    }
+
+    #[test]
+    fn decrypt_with_wrong_password_fails() {
+        let jwk = validator_key();
+        let keystore = Keystore::encrypt_with_params(&jwk, b"secret", TEST_PARAMS).unwrap();
+
+        assert!(matches!(
+            keystore.decrypt(b"wrong"),
+            Err(KeystoreError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn change_password_keeps_key() {
+        let jwk = validator_key();
+        let keystore = Keystore::encrypt_with_params(&jwk, b"old", TEST_PARAMS).unwrap();
+
+        let keystore = keystore.change_password(b"old", b"new").unwrap();
+        assert!(keystore.decrypt(b"old").is_err());
+        assert_eq!(address(&keystore.decrypt(b"new").unwrap()), address(&jwk));
+    }
+
+    #[test]
+    fn parse_plaintext_and_encrypted_key_files() {
+        let jwk = validator_key();
+        let plaintext = jwk.to_string();
+        let encrypted = serde_json::to_string(
+            &Keystore::encrypt_with_params(&jwk, b"secret", TEST_PARAMS).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            address(&parse_key(&plaintext, None).unwrap()),
+            address(&jwk)
+        );
+        assert!(matches!(
+            parse_key(&encrypted, None),
+            Err(KeystoreError::PasswordRequired)
+        ));
+        assert_eq!(
+            address(&parse_key(&encrypted, Some(b"secret")).unwrap()),
+            address(&jwk)
+        );
+    }
+}
@@ -4,18 +4,30 @@ extern crate diesel;
 #[macro_use]
 extern crate diesel_migrations;
 
+pub mod audit_log;
 pub mod bundle;
 pub mod bundler;
+pub mod bundler_keys;
 pub mod consts;
 pub mod context;
 pub mod contract_gateway;
 pub mod cron;
 pub mod database;
+pub mod epoch_report;
+pub mod events;
 pub mod hardware;
 pub mod http;
+pub mod inclusion;
 pub mod key_manager;
+pub mod key_rotation;
+pub mod keystore;
+pub mod leader;
+pub mod readiness;
 pub mod retry;
 pub mod server;
+pub mod signing_policy;
+pub mod signing_pool;
+pub mod slashing;
 pub mod state;
 pub mod types;
 pub mod utils;
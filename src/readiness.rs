@@ -0,0 +1,169 @@
+//! Gate that keeps the validator from signing on outdated state
+//!
+//! Role and epoch come from the contract, block height from the network.
+//! Until both were synced since start up, while either sync is too old, and
+//! while contract state lags too many blocks behind the network, the
+//! validator refuses to cosign.
+
+use std::{fmt, time::Duration};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::state::{State, SyncMark};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReadinessConfig {
+    /// Longest time since the last successful sync
+    pub max_sync_age: Duration,
+    /// Most blocks the network moved on since the last successful contract sync
+    pub max_block_lag: u128,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self {
+            max_sync_age: Duration::from_secs(300),
+            max_block_lag: 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncSource {
+    Contract,
+    Network,
+}
+
+impl fmt::Display for SyncSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncSource::Contract => write!(f, "contract state"),
+            SyncSource::Network => write!(f, "network info"),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum NotReady {
+    #[error("{0} was not synced since start up")]
+    NotSynced(SyncSource),
+    #[error("{sync} was last synced {age_secs} seconds ago")]
+    Outdated { sync: SyncSource, age_secs: u64 },
+    #[error("{sync} was last synced {blocks} blocks ago")]
+    BehindBlocks { sync: SyncSource, blocks: u128 },
+}
+
+/// Check that contract state and network info are recent enough to sign
+pub fn check_readiness(state: &State, config: &ReadinessConfig) -> Result<(), NotReady> {
+    let network = check_sync(config, SyncSource::Network, state.network_synced())?;
+    let contract = check_sync(config, SyncSource::Contract, state.contract_synced())?;
+
+    // Current block height is whatever the last network sync saw, so only
+    // contract state can lag behind it
+    let blocks = network.block.saturating_sub(contract.block);
+    if blocks > config.max_block_lag {
+        return Err(NotReady::BehindBlocks {
+            sync: SyncSource::Contract,
+            blocks,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_sync(
+    config: &ReadinessConfig,
+    sync: SyncSource,
+    mark: Option<SyncMark>,
+) -> Result<SyncMark, NotReady> {
+    let mark = mark.ok_or(NotReady::NotSynced(sync))?;
+
+    let age = mark.at.elapsed();
+    if age > config.max_sync_age {
+        return Err(NotReady::Outdated {
+            sync,
+            age_secs: age.as_secs(),
+        });
+    }
+
+    Ok(mark)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::state::generate_state;
+
+    use super::{check_readiness, NotReady, ReadinessConfig, SyncSource};
+
+    #[test]
+    fn not_ready_until_both_syncs_succeeded() {
+        let state = generate_state();
+        let config = ReadinessConfig::default();
+
+        assert_eq!(
+            check_readiness(&state, &config),
+            Err(NotReady::NotSynced(SyncSource::Network))
+        );
+
+        state.mark_network_synced();
+        assert_eq!(
+            check_readiness(&state, &config),
+            Err(NotReady::NotSynced(SyncSource::Contract))
+        );
+
+        state.mark_contract_synced();
+        assert_eq!(check_readiness(&state, &config), Ok(()));
+    }
+
+    #[test]
+    fn not_ready_when_contract_sync_falls_behind_by_blocks() {
+        let state = generate_state();
+        let config = ReadinessConfig::default();
+
+        state.set_current_block(100);
+        state.mark_network_synced();
+        state.mark_contract_synced();
+
+        state.set_current_block(105);
+        state.mark_network_synced();
+        assert_eq!(check_readiness(&state, &config), Ok(()));
+
+        state.set_current_block(106);
+        state.mark_network_synced();
+        assert_eq!(
+            check_readiness(&state, &config),
+            Err(NotReady::BehindBlocks {
+                sync: SyncSource::Contract,
+                blocks: 6
+            })
+        );
+
+        state.mark_contract_synced();
+        assert_eq!(check_readiness(&state, &config), Ok(()));
+    }
+
+    #[test]
+    fn not_ready_when_sync_is_too_old() {
+        let state = generate_state();
+        let config = ReadinessConfig {
+            max_sync_age: Duration::ZERO,
+            ..ReadinessConfig::default()
+        };
+
+        state.mark_network_synced();
+        state.mark_contract_synced();
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(matches!(
+            check_readiness(&state, &config),
+            Err(NotReady::Outdated {
+                sync: SyncSource::Network,
+                ..
+            })
+        ));
+    }
+}
@@ -6,6 +6,49 @@ use actix_web::{
 };
 use derive_more::{Display, Error};
 use openssl::error::ErrorStack;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    key_manager::KeyManagerError,
+    key_rotation::KeyRotationError,
+    readiness::NotReady,
+    signing_policy::{PolicyRule, PolicyViolation},
+    signing_pool::SigningPoolError,
+};
+
+/// Stable identifier of an error, clients should branch on this rather than on the message
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InternalError,
+    BadRequest,
+    Timeout,
+    NotFound,
+    Unauthorized,
+    InvalidValidatorAddress,
+    NotCosigner,
+    PolicyViolation,
+    InvalidBundlerSignature,
+    ConflictingPromise,
+    DuplicateTransaction,
+    BatchTooLarge,
+    SignerUnavailable,
+    KeyRotationFailed,
+    Overloaded,
+    NotReady,
+    NotLeader,
+    InvalidReceipt,
+}
+
+/// JSON envelope every route responds with on error
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
 
 #[warn(dead_code)]
 #[derive(Debug, Display, Error)]
@@ -18,13 +61,115 @@ pub enum ValidatorServerError {
 
     #[display(fmt = "timeout")]
     Timeout,
+
+    #[display(fmt = "not found")]
+    NotFound,
+
+    #[display(fmt = "Missing or invalid admin token")]
+    Unauthorized,
+
+    #[display(fmt = "Invalid validator address")]
+    InvalidValidatorAddress,
+
+    #[display(fmt = "Not a cosigner for the requested block")]
+    NotCosigner,
+
+    #[display(fmt = "{}", reason)]
+    PolicyViolation {
+        rule: PolicyRule,
+        reason: String,
+        details: Option<Value>,
+    },
+
+    #[display(fmt = "Invalid bundler signature")]
+    InvalidBundlerSignature,
+
+    #[display(fmt = "Conflicting promise for already signed transaction")]
+    ConflictingPromise,
+
+    #[display(fmt = "Duplicate transaction id in batch")]
+    DuplicateTransaction,
+
+    #[display(fmt = "Batch holds more than {} sign requests", max)]
+    BatchTooLarge { max: usize },
+
+    #[display(fmt = "Signer is unavailable")]
+    SignerUnavailable,
+
+    #[display(fmt = "{}", reason)]
+    KeyRotationFailed { reason: String },
+
+    /// Signing queue is full, client should retry after `retry_after` seconds
+    #[display(fmt = "Validator is overloaded, retry later")]
+    Overloaded { retry_after: u64 },
+
+    /// Validator state is not synced recently enough to sign
+    #[display(fmt = "Validator is not ready: {}", reason)]
+    NotReady { reason: String },
+
+    #[display(fmt = "Not the leader for the requested block")]
+    NotLeader,
+
+    #[display(fmt = "Invalid validator receipt")]
+    InvalidReceipt,
+}
+
+impl ValidatorServerError {
+    pub fn code(&self) -> ErrorCode {
+        match *self {
+            ValidatorServerError::InternalError => ErrorCode::InternalError,
+            ValidatorServerError::BadClientData => ErrorCode::BadRequest,
+            ValidatorServerError::Timeout => ErrorCode::Timeout,
+            ValidatorServerError::NotFound => ErrorCode::NotFound,
+            ValidatorServerError::Unauthorized => ErrorCode::Unauthorized,
+            ValidatorServerError::InvalidValidatorAddress => ErrorCode::InvalidValidatorAddress,
+            ValidatorServerError::NotCosigner => ErrorCode::NotCosigner,
+            ValidatorServerError::PolicyViolation { .. } => ErrorCode::PolicyViolation,
+            ValidatorServerError::InvalidBundlerSignature => ErrorCode::InvalidBundlerSignature,
+            ValidatorServerError::ConflictingPromise => ErrorCode::ConflictingPromise,
+            ValidatorServerError::DuplicateTransaction => ErrorCode::DuplicateTransaction,
+            ValidatorServerError::BatchTooLarge { .. } => ErrorCode::BatchTooLarge,
+            ValidatorServerError::SignerUnavailable => ErrorCode::SignerUnavailable,
+            ValidatorServerError::KeyRotationFailed { .. } => ErrorCode::KeyRotationFailed,
+            ValidatorServerError::Overloaded { .. } => ErrorCode::Overloaded,
+            ValidatorServerError::NotReady { .. } => ErrorCode::NotReady,
+            ValidatorServerError::NotLeader => ErrorCode::NotLeader,
+            ValidatorServerError::InvalidReceipt => ErrorCode::InvalidReceipt,
+        }
+    }
+
+    /// Policy violations report the failed rule along with the rule's own details,
+    /// oversized batches report the maximum batch size
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            ValidatorServerError::PolicyViolation { rule, details, .. } => {
+                let mut merged = json!({ "rule": rule });
+                if let (Some(Value::Object(extra)), Some(obj)) = (details, merged.as_object_mut()) {
+                    obj.extend(extra.clone());
+                }
+                Some(merged)
+            }
+            ValidatorServerError::BatchTooLarge { max } => Some(json!({ "max_batch_size": max })),
+            _ => None,
+        }
+    }
+
+    pub fn error_body(&self) -> ErrorBody {
+        ErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            details: self.details(),
+        }
+    }
 }
 
 impl error::ResponseError for ValidatorServerError {
     fn error_response(&self) -> HttpResponse {
-        HttpResponseBuilder::new(self.status_code())
-            .insert_header((header::CONTENT_TYPE, "text/html; charset=utf-8"))
-            .body(self.to_string())
+        let mut res = HttpResponseBuilder::new(self.status_code());
+        if let ValidatorServerError::Overloaded { retry_after } = self {
+            res.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        res.json(self.error_body())
     }
 
     fn status_code(&self) -> StatusCode {
@@ -32,6 +177,74 @@ impl error::ResponseError for ValidatorServerError {
             ValidatorServerError::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
             ValidatorServerError::BadClientData => StatusCode::BAD_REQUEST,
             ValidatorServerError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ValidatorServerError::NotFound => StatusCode::NOT_FOUND,
+            ValidatorServerError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ValidatorServerError::InvalidValidatorAddress => StatusCode::BAD_REQUEST,
+            ValidatorServerError::NotCosigner => StatusCode::BAD_REQUEST,
+            ValidatorServerError::PolicyViolation { .. } => StatusCode::BAD_REQUEST,
+            ValidatorServerError::InvalidBundlerSignature => StatusCode::BAD_REQUEST,
+            ValidatorServerError::ConflictingPromise => StatusCode::CONFLICT,
+            ValidatorServerError::DuplicateTransaction => StatusCode::BAD_REQUEST,
+            ValidatorServerError::BatchTooLarge { .. } => StatusCode::BAD_REQUEST,
+            ValidatorServerError::SignerUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ValidatorServerError::KeyRotationFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ValidatorServerError::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            ValidatorServerError::NotReady { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            ValidatorServerError::NotLeader => StatusCode::BAD_REQUEST,
+            ValidatorServerError::InvalidReceipt => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl From<PolicyViolation> for ValidatorServerError {
+    fn from(violation: PolicyViolation) -> Self {
+        ValidatorServerError::PolicyViolation {
+            rule: violation.rule,
+            reason: violation.reason,
+            details: violation.details,
+        }
+    }
+}
+
+impl From<KeyManagerError> for ValidatorServerError {
+    fn from(e: KeyManagerError) -> Self {
+        log::error!("Error occurred while signing - {}", e);
+        match e {
+            KeyManagerError::Crypto(_) => ValidatorServerError::InternalError,
+            KeyManagerError::SignerUnavailable(_) | KeyManagerError::Signer(_) => {
+                ValidatorServerError::SignerUnavailable
+            }
+        }
+    }
+}
+
+impl From<KeyRotationError> for ValidatorServerError {
+    fn from(e: KeyRotationError) -> Self {
+        ValidatorServerError::KeyRotationFailed {
+            reason: e.to_string(),
+        }
+    }
+}
+
+impl From<SigningPoolError> for ValidatorServerError {
+    fn from(e: SigningPoolError) -> Self {
+        match e {
+            SigningPoolError::Busy { retry_after } => ValidatorServerError::Overloaded {
+                // Retry-After has a resolution of seconds
+                retry_after: retry_after.as_secs().max(1),
+            },
+            SigningPoolError::Failed => {
+                log::error!("Error occurred while signing - {}", e);
+                ValidatorServerError::InternalError
+            }
+        }
+    }
+}
+
+impl From<NotReady> for ValidatorServerError {
+    fn from(e: NotReady) -> Self {
+        ValidatorServerError::NotReady {
+            reason: e.to_string(),
         }
     }
 }
@@ -56,3 +269,53 @@ impl From<diesel::result::Error> for ValidatorServerError {
         ValidatorServerError::InternalError
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::to_bytes, http::header, ResponseError};
+    use serde_json::json;
+
+    use crate::signing_policy::PolicyRule;
+
+    use super::{ErrorBody, ErrorCode, ValidatorServerError};
+
+    #[actix_web::test]
+    async fn policy_violation_renders_rule_and_details() {
+        let err = ValidatorServerError::PolicyViolation {
+            rule: PolicyRule::PromiseWindow,
+            reason: "Invalid block number".to_string(),
+            details: Some(json!({ "min_block": "395", "max_block": "405" })),
+        };
+
+        let res = err.error_response();
+        assert_eq!(res.status(), 400);
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let body: ErrorBody = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: ErrorCode::PolicyViolation,
+                message: "Invalid block number".to_string(),
+                details: Some(json!({
+                    "rule": "promise_window",
+                    "min_block": "395",
+                    "max_block": "405",
+                })),
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn overloaded_sets_retry_after() {
+        let err = ValidatorServerError::Overloaded { retry_after: 2 };
+
+        let res = err.error_response();
+        assert_eq!(res.status(), 503);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "2");
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let body: ErrorBody = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, ErrorCode::Overloaded);
+    }
+}
@@ -12,13 +12,25 @@ use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
     PgConnection,
 };
-use log::info;
+use log::{info, warn};
+use routes::admin::{reload_key, AdminToken};
 use routes::get_tx::get_tx;
 use routes::index::index;
+use routes::jobs::jobs;
+use routes::ready::ready;
 use routes::status::status;
+use tokio::sync::mpsc;
 
 use crate::{
-    database::queries::QueryContext, key_manager, server::routes::sign::sign_route,
+    cron::scheduler::JobsAccess,
+    database::queries::QueryContext,
+    events::EventsAccess,
+    key_manager,
+    key_rotation::ReloadReply,
+    server::error::ValidatorServerError,
+    server::routes::leader::{receipts_route, report_route},
+    server::routes::sign::{sign_batch_route, sign_route},
+    signing_pool::SigningPoolAccess,
     state::ValidatorStateAccess, context::{BundlerAccess, ValidatorAddressAccess},
 };
 
@@ -38,6 +50,9 @@ where
         + BundlerAccess
         + ValidatorAddressAccess
         + QueryContext
+        + EventsAccess
+        + JobsAccess
+        + SigningPoolAccess
         + Clone
         + Send
         + 'static,
@@ -53,13 +68,31 @@ where
 
             let app = App::new()
                 .app_data(Data::new(runtime_context.clone()))
+                .app_data(web::JsonConfig::default().error_handler(|err, _| {
+                    warn!("Failed to parse request body - {}", err);
+                    ValidatorServerError::BadClientData.into()
+                }))
                 .wrap(Logger::default())
                 .route("/", web::get().to(index::<Context, KeyManager>))
                 .route("/status", web::get().to(status::<Context, KeyManager>))
+                .route("/ready", web::get().to(ready::<Context, KeyManager>))
+                .route("/jobs", web::get().to(jobs::<Context>))
                 .route("/tx/{tx_id}", web::get().to(get_tx::<Context>))
                 .service(
                     web::scope("/cosigner")
-                        .route("/sign", web::post().to(sign_route::<Context, KeyManager>)),
+                        .route("/sign", web::post().to(sign_route::<Context, KeyManager>))
+                        .route(
+                            "/sign/batch",
+                            web::post().to(sign_batch_route::<Context, KeyManager>),
+                        ),
+                )
+                .service(
+                    web::scope("/leader")
+                        .route(
+                            "/receipts",
+                            web::post().to(receipts_route::<Context, KeyManager>),
+                        )
+                        .route("/reports/{epoch}", web::get().to(report_route::<Context>)),
                 )
                 .service(web::scope("/idle").route("/", web::get().to(index::<Context, KeyManager>)));
 
@@ -75,3 +108,38 @@ where
     .run()
     .await
 }
+
+/// Server for operator actions
+///
+/// Refuses to listen on anything but a loopback address unless requests have
+/// to carry `token`.
+pub async fn run_admin_server(
+    listen: SocketAddr,
+    token: AdminToken,
+    reloads: mpsc::Sender<ReloadReply>,
+) -> std::io::Result<()> {
+    if token.0.is_none() && !listen.ip().is_loopback() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!(
+                "admin server on non-loopback address {} requires ADMIN_TOKEN",
+                listen
+            ),
+        ));
+    }
+
+    info!("Starting up admin HTTP server on {}...", listen);
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(Data::new(token.clone()))
+            .app_data(Data::new(reloads.clone()))
+            .wrap(Logger::default())
+            .route("/reload-key", web::post().to(reload_key))
+    })
+    .workers(1)
+    .shutdown_timeout(5)
+    .bind(listen)?
+    .run()
+    .await
+}
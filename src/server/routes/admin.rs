@@ -0,0 +1,145 @@
+use actix_web::{http::header, web::Data, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{key_rotation::ReloadReply, server::error::ValidatorServerError};
+
+/// Token admin requests must carry as `Authorization: Bearer <token>`
+///
+/// Without a token, the admin server only listens on a loopback address.
+#[derive(Clone, Debug, Default)]
+pub struct AdminToken(pub Option<String>);
+
+impl AdminToken {
+    fn authorize(&self, req: &HttpRequest) -> Result<(), ValidatorServerError> {
+        let expected = match &self.0 {
+            Some(token) => token.as_bytes(),
+            None => return Ok(()),
+        };
+
+        let given = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::as_bytes)
+            .unwrap_or_default();
+
+        // Compared in constant time, only the length may leak
+        if given.len() == expected.len() && openssl::memcmp::eq(given, expected) {
+            Ok(())
+        } else {
+            Err(ValidatorServerError::Unauthorized)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReloadKeyBody {
+    pub address: String,
+}
+
+/// Reload validator key and wait for the rotation to finish
+pub async fn reload_key(
+    req: HttpRequest,
+    token: Data<AdminToken>,
+    reloads: Data<mpsc::Sender<ReloadReply>>,
+) -> actix_web::Result<HttpResponse, ValidatorServerError> {
+    token.authorize(&req)?;
+
+    let (reply, result) = oneshot::channel();
+    reloads.send(reply).await.map_err(|_| {
+        log::error!("Key reloader is not running");
+        ValidatorServerError::InternalError
+    })?;
+
+    let address = result
+        .await
+        .map_err(|_| ValidatorServerError::InternalError)??;
+
+    Ok(HttpResponse::Ok().json(ReloadKeyBody { address }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web::{self, Data},
+        App,
+    };
+    use tokio::sync::mpsc;
+
+    use crate::{
+        key_rotation::{KeyRotationError, ReloadReply},
+        server::error::{ErrorBody, ErrorCode},
+    };
+
+    use super::{reload_key, AdminToken, ReloadKeyBody};
+
+    #[actix_web::test]
+    async fn reload_reports_new_address_or_failure() {
+        let (reloads, mut requests) = mpsc::channel::<ReloadReply>(1);
+        actix_rt::spawn(async move {
+            let reply = requests.recv().await.unwrap();
+            reply.send(Ok("new_address".to_string())).unwrap();
+            let reply = requests.recv().await.unwrap();
+            reply
+                .send(Err(KeyRotationError::NotRegistered("other".to_string())))
+                .unwrap();
+        });
+
+        let app = App::new()
+            .app_data(Data::new(AdminToken::default()))
+            .app_data(Data::new(reloads))
+            .route("/reload-key", web::post().to(reload_key));
+        let app = init_service(app).await;
+
+        let req = TestRequest::post().uri("/reload-key").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: ReloadKeyBody = read_body_json(res).await;
+        assert_eq!(body.address, "new_address");
+
+        let req = TestRequest::post().uri("/reload-key").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::KeyRotationFailed);
+    }
+
+    #[actix_web::test]
+    async fn reload_requires_token_when_configured() {
+        let (reloads, mut requests) = mpsc::channel::<ReloadReply>(1);
+        actix_rt::spawn(async move {
+            let reply = requests.recv().await.unwrap();
+            reply.send(Ok("new_address".to_string())).unwrap();
+        });
+
+        let app = App::new()
+            .app_data(Data::new(AdminToken(Some("secret".to_string()))))
+            .app_data(Data::new(reloads))
+            .route("/reload-key", web::post().to(reload_key));
+        let app = init_service(app).await;
+
+        let req = TestRequest::post().uri("/reload-key").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::Unauthorized);
+
+        let req = TestRequest::post()
+            .uri("/reload-key")
+            .insert_header(("Authorization", "Bearer wrong!"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::post()
+            .uri("/reload-key")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
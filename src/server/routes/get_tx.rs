@@ -1,4 +1,7 @@
-use actix_web::{web::Data, HttpResponse};
+use actix_web::{
+    web::{Data, Path},
+    HttpResponse,
+};
 
 use crate::{
     database::{models::Transaction, schema::transactions::dsl::*},
@@ -8,22 +11,116 @@ use diesel::prelude::*;
 
 pub async fn get_tx<Context>(
     ctx: Data<Context>,
-    path: (String,),
+    path: Path<(String,)>,
 ) -> actix_web::Result<HttpResponse, ValidatorServerError>
 where
     Context: RuntimeContext,
 {
     let conn = ctx.get_db_connection();
+    let (tx_id,) = path.into_inner();
     let res = actix_rt::task::spawn_blocking(move || {
         transactions
-            .filter(id.eq(path.0))
+            .filter(id.eq(tx_id))
             .first::<Transaction>(&conn)
+            .optional()
     })
-    .await?;
+    .await??;
+
+    match res {
+        Some(r) => Ok(HttpResponse::Ok().json(r)),
+        None => Err(ValidatorServerError::NotFound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web::{self, Data},
+        App,
+    };
+    use diesel::RunQueryDsl;
+    use reqwest::StatusCode;
+
+    use crate::{
+        context::{test_utils::test_context, AppContext},
+        database::{
+            models::{Block, Epoch, NewTransaction},
+            schema::transactions,
+        },
+        http::reqwest::mock::MockHttpClient,
+        server::{
+            error::{ErrorBody, ErrorCode},
+            RuntimeContext,
+        },
+    };
+
+    use super::get_tx;
+
+    #[actix_web::test]
+    async fn get_unknown_tx_yields_not_found() {
+        let (key_manager, _) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/tx/{tx_id}",
+            web::get().to(get_tx::<AppContext<MockHttpClient>>),
+        );
+
+        let app = init_service(app).await;
+
+        let req = TestRequest::get()
+            .uri("/tx/dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-G0")
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::NotFound);
+    }
+
+    #[actix_web::test]
+    async fn get_tx_returns_stored_bundler_promise() {
+        let (key_manager, _) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+
+        let tx_id = "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-G1";
+        diesel::insert_into(transactions::table)
+            .values(&NewTransaction {
+                id: tx_id.to_string(),
+                epoch: Epoch(1),
+                block_promised: Block(400),
+                block_actual: None,
+                signature: "validator_signature".as_bytes().to_vec(),
+                validated: false,
+                bundle_id: None,
+                size: Some(10),
+                fee: Some("20".to_string()),
+                currency: Some("arweave".to_string()),
+                bundler_signature: Some("bundler_signature".as_bytes().to_vec()),
+            })
+            .execute(&ctx.get_db_connection())
+            .unwrap();
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/tx/{tx_id}",
+            web::get().to(get_tx::<AppContext<MockHttpClient>>),
+        );
+
+        let app = init_service(app).await;
+
+        let req = TestRequest::get()
+            .uri(&format!("/tx/{}", tx_id))
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
 
-    if let Ok(r) = res {
-        Ok(HttpResponse::Ok().json(r))
-    } else {
-        Ok(HttpResponse::NotFound().finish())
+        let body: serde_json::Value = read_body_json(res).await;
+        assert_eq!(body["size"], 10);
+        assert_eq!(body["fee"], "20");
+        assert_eq!(body["currency"], "arweave");
+        assert!(body["bundler_signature"].is_array());
     }
 }
@@ -0,0 +1,64 @@
+use actix_web::{web::Data, HttpResponse};
+
+use crate::{cron::scheduler::JobsAccess, server::error::ValidatorServerError};
+
+/// Schedule and last run of every cron job, by job name
+pub async fn jobs<Context>(
+    ctx: Data<Context>,
+) -> actix_web::Result<HttpResponse, ValidatorServerError>
+where
+    Context: JobsAccess,
+{
+    Ok(HttpResponse::Ok().json(ctx.jobs().snapshot()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web::{self, Data},
+        App,
+    };
+    use serde_json::Value;
+
+    use crate::{
+        context::{test_utils::test_context, AppContext},
+        cron::scheduler::{run_once, JobConfig, JobsAccess},
+        http::reqwest::mock::MockHttpClient,
+        key_manager::test_utils::test_keys,
+    };
+
+    use super::jobs;
+
+    #[actix_web::test]
+    async fn jobs_lists_last_run_of_every_job() {
+        let (key_manager, _) = test_keys();
+        let ctx = test_context(key_manager);
+
+        ctx.jobs().register("network-info", JobConfig::every(30));
+        ctx.jobs()
+            .register("contract-updates", JobConfig::every(30));
+        run_once(ctx.jobs(), "network-info", Duration::from_secs(1), async {
+            Ok(())
+        })
+        .await;
+
+        let app = App::new()
+            .app_data(Data::new(ctx.clone()))
+            .route("/jobs", web::get().to(jobs::<AppContext<MockHttpClient>>));
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/jobs").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: Value = read_body_json(res).await;
+        assert_eq!(body["network-info"]["last_outcome"], "success");
+        assert_eq!(body["network-info"]["config"]["interval"], 30);
+        assert_eq!(body["network-info"]["running"], false);
+        assert!(body["contract-updates"]["last_start"].is_null());
+    }
+}
@@ -0,0 +1,268 @@
+use actix_web::{
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use futures::future::join_all;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    key_manager,
+    leader::{self, receipt_epoch, Receipt},
+    readiness::check_readiness,
+    server::{
+        error::{ErrorBody, ValidatorServerError},
+        routes::sign::Config,
+        RuntimeContext,
+    },
+    signing_pool::SigningPoolAccess,
+};
+
+/// Receipt the leader didn't store
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct RejectedReceipt {
+    pub id: String,
+    pub validator: String,
+    #[serde(flatten)]
+    pub error: ErrorBody,
+}
+
+impl RejectedReceipt {
+    fn new(receipt: &Receipt, err: ValidatorServerError) -> Self {
+        RejectedReceipt {
+            id: receipt.request.id().to_string(),
+            validator: receipt.request.validator().to_string(),
+            error: err.error_body(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReceiptsResponse {
+    /// Receipts stored now or earlier
+    pub accepted: usize,
+    pub rejected: Vec<RejectedReceipt>,
+}
+
+/// Take receipts other validators issued during an epoch led by this validator
+///
+/// Each receipt has to be signed by the validator the request was sent to and
+/// carry a valid bundler signature. Receipts are checked independently, the
+/// ones that fail are listed in the response.
+pub async fn receipts_route<Context, KeyManager>(
+    ctx: Data<Context>,
+    body: Json<Vec<Receipt>>,
+) -> actix_web::Result<HttpResponse, ValidatorServerError>
+where
+    Context: Config<KeyManager> + RuntimeContext + SigningPoolAccess + Send,
+    KeyManager: key_manager::KeyManager + Clone + Send + 'static,
+{
+    let receipts = body.into_inner();
+
+    check_readiness(ctx.get_validator_state(), ctx.readiness())?;
+
+    let known = {
+        let conn = ctx.get_db_connection();
+        let addresses = receipts
+            .iter()
+            .map(|receipt| receipt.request.validator().to_string())
+            .collect();
+        actix_rt::task::spawn_blocking(move || leader::known_validators(&conn, addresses)).await??
+    };
+
+    let key_manager = ctx.key_manager();
+    let mut rejected = Vec::new();
+    let mut verifying = Vec::with_capacity(receipts.len());
+    for receipt in receipts {
+        let epoch = match receipt_epoch(ctx.get_validator_state(), receipt.request.block()) {
+            Some(epoch) => epoch,
+            None => {
+                rejected.push(RejectedReceipt::new(
+                    &receipt,
+                    ValidatorServerError::NotLeader,
+                ));
+                continue;
+            }
+        };
+
+        if !known.contains(receipt.request.validator()) {
+            warn!(
+                "Receipt for {} from unknown validator {}",
+                receipt.request.id(),
+                receipt.request.validator()
+            );
+            rejected.push(RejectedReceipt::new(
+                &receipt,
+                ValidatorServerError::InvalidReceipt,
+            ));
+            continue;
+        }
+
+        let queued = receipt.clone();
+        let key_manager = key_manager.clone();
+        match ctx.signing_pool().submit(move || {
+            let valid = match queued.verify(key_manager.bundler_address()) {
+                Ok(()) => queued.request.verify_blocking(&key_manager),
+                Err(err) => {
+                    warn!("Invalid receipt for {}: {}", queued.request.id(), err);
+                    Ok(false)
+                }
+            };
+            (queued, epoch, valid)
+        }) {
+            Ok(verification) => verifying.push(verification),
+            Err(err) => rejected.push(RejectedReceipt::new(&receipt, err.into())),
+        }
+    }
+
+    let mut accepted = Vec::new();
+    for res in join_all(verifying).await {
+        match res.map_err(|_| ValidatorServerError::InternalError)? {
+            (receipt, epoch, Ok(true)) => accepted.push(receipt.to_new_receipt(epoch)),
+            (receipt, _, Ok(false)) => rejected.push(RejectedReceipt::new(
+                &receipt,
+                ValidatorServerError::InvalidReceipt,
+            )),
+            (_, _, Err(())) => return Err(ValidatorServerError::InternalError),
+        }
+    }
+
+    let count = accepted.len();
+    if !accepted.is_empty() {
+        let conn = ctx.get_db_connection();
+        actix_rt::task::spawn_blocking(move || leader::store_receipts(&conn, &accepted)).await??;
+    }
+
+    Ok(HttpResponse::Ok().json(ReceiptsResponse {
+        accepted: count,
+        rejected,
+    }))
+}
+
+/// Report on an epoch this validator led
+pub async fn report_route<Context>(
+    ctx: Data<Context>,
+    path: Path<(String,)>,
+) -> actix_web::Result<HttpResponse, ValidatorServerError>
+where
+    Context: RuntimeContext,
+{
+    let (epoch,) = path.into_inner();
+    let epoch: u128 = epoch
+        .parse()
+        .map_err(|_| ValidatorServerError::BadClientData)?;
+
+    let conn = ctx.get_db_connection();
+    let report = actix_rt::task::spawn_blocking(move || leader::get_report(&conn, epoch)).await??;
+
+    match report {
+        Some(report) => Ok(HttpResponse::Ok().json(report)),
+        None => Err(ValidatorServerError::NotFound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::{header::ContentType, StatusCode},
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web::{self, Data},
+        App,
+    };
+    use data_encoding::BASE64URL_NOPAD;
+    use diesel::{ExpressionMethods, RunQueryDsl};
+
+    use crate::{
+        context::{test_utils::test_context, AppContext},
+        database::schema::validators,
+        http::reqwest::mock::MockHttpClient,
+        key_manager::{test_utils::test_keys, KeyManager},
+        leader::Receipt,
+        server::{
+            error::ErrorCode,
+            routes::sign::{test_utils::test_message, Config},
+            RuntimeContext,
+        },
+        state::{ScheduledEpoch, ValidatorRole, ValidatorStateAccess},
+    };
+
+    use super::{receipts_route, ReceiptsResponse};
+
+    #[actix_web::test]
+    async fn leader_stores_receipts_of_known_validators() {
+        let (key_manager, bundler_private_key) = test_keys();
+        let ctx = test_context(key_manager);
+        let (cosigner, _) = test_keys();
+
+        diesel::insert_into(validators::table)
+            .values(validators::address.eq(cosigner.validator_address()))
+            .on_conflict_do_nothing()
+            .execute(&ctx.get_db_connection())
+            .unwrap();
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(receipts_route::<AppContext<MockHttpClient>, _>),
+        );
+        let app = init_service(app).await;
+
+        let bundler_address = ctx.key_manager().bundler_address().to_string();
+        let receipt = |tx: &str, block: u128| {
+            let request = test_message(
+                &bundler_private_key,
+                block,
+                cosigner.validator_address().to_string(),
+                tx,
+            );
+            let message = request
+                .validator_signature_message(&bundler_address)
+                .unwrap();
+            let signature = BASE64URL_NOPAD.encode(&cosigner.validator_sign(&message).unwrap());
+            Receipt::new(request, cosigner.validator_public_key(), signature)
+        };
+
+        let mut forged = receipt("dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-L3", 5);
+        forged.signature = receipt("dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-L4", 5).signature;
+        let receipts = vec![
+            receipt("dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-L2", 5),
+            forged,
+        ];
+
+        // Not leading the epoch yet
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&receipts)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: ReceiptsResponse = read_body_json(res).await;
+        assert_eq!(body.accepted, 0);
+        assert!(body
+            .rejected
+            .iter()
+            .all(|rejected| rejected.error.code == ErrorCode::NotLeader));
+
+        ctx.get_validator_state().schedule_epoch(ScheduledEpoch {
+            seq: 0,
+            height: 0,
+            role: ValidatorRole::Leader,
+        });
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&receipts)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: ReceiptsResponse = read_body_json(res).await;
+        assert_eq!(body.accepted, 1);
+        assert_eq!(body.rejected.len(), 1);
+        assert_eq!(
+            body.rejected[0].id,
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-L3"
+        );
+        assert_eq!(body.rejected[0].error.code, ErrorCode::InvalidReceipt);
+    }
+}
@@ -1,5 +1,9 @@
+pub mod admin;
 pub mod get_tx;
 pub mod index;
+pub mod jobs;
+pub mod leader;
+pub mod ready;
 pub mod sign;
 pub mod test;
 pub mod status;
@@ -0,0 +1,73 @@
+use actix_web::{web::Data, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    key_manager,
+    readiness::check_readiness,
+    server::{error::ValidatorServerError, routes::sign::Config},
+};
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReadyBody {
+    pub ready: bool,
+}
+
+/// Responds with 503 and code `not_ready` while sign requests are refused
+pub async fn ready<Context, KeyManager>(
+    ctx: Data<Context>,
+) -> actix_web::Result<HttpResponse, ValidatorServerError>
+where
+    Context: Config<KeyManager>,
+    KeyManager: key_manager::KeyManager,
+{
+    check_readiness(ctx.get_validator_state(), ctx.readiness())?;
+
+    Ok(HttpResponse::Ok().json(ReadyBody { ready: true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web::{self, Data},
+        App,
+    };
+
+    use crate::{
+        context::{test_utils::test_context, AppContext},
+        http::reqwest::mock::MockHttpClient,
+        key_manager::test_utils::test_keys,
+        readiness::ReadinessConfig,
+        server::error::{ErrorBody, ErrorCode},
+        state::ValidatorStateAccess,
+    };
+
+    use super::{ready, ReadyBody};
+
+    #[actix_web::test]
+    async fn ready_reflects_readiness_check() {
+        let (key_manager, _) = test_keys();
+        let ctx = test_context(key_manager).with_readiness(ReadinessConfig::default());
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/ready",
+            web::get().to(ready::<AppContext<MockHttpClient>, _>),
+        );
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/ready").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: ReadyBody = read_body_json(res).await;
+        assert!(body.ready);
+
+        ctx.get_validator_state().set_current_block(10);
+
+        let req = TestRequest::get().uri("/ready").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::NotReady);
+    }
+}
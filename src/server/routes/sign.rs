@@ -2,21 +2,38 @@ use actix_web::{
     web::{Data, Json},
     HttpResponse,
 };
-use bundlr_sdk::deep_hash::{deep_hash, DeepHashChunk, ONE_AS_BUFFER};
+use bundlr_contracts_validators::slashing::Proposal as SlashProposal;
+use bundlr_sdk::{
+    deep_hash::{deep_hash, DeepHashChunk, ONE_AS_BUFFER},
+    deep_hash_sync::deep_hash_sync,
+};
 
 use data_encoding::BASE64URL_NOPAD;
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-use log::error;
+use diesel::{
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+};
+use futures::future::join_all;
+use log::{error, warn};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
 
 use crate::{
+    audit_log,
+    bundler_keys::BundlerPublicKey,
     consts::{BUNDLR_AS_BUFFER, VALIDATOR_AS_BUFFER},
     database::{
-        models::{Epoch, NewTransaction},
-        schema::transactions::dsl::*,
+        models::{Epoch, NewEquivocation, NewTransaction, Transaction},
+        schema::{equivocations, transactions::dsl::*},
     },
+    events::{EventsAccess, ValidatorEvent},
     key_manager,
-    server::{error::ValidatorServerError, RuntimeContext},
+    readiness::{check_readiness, ReadinessConfig},
+    server::{
+        error::{ErrorCode, ValidatorServerError},
+        RuntimeContext,
+    },
+    signing_policy::SigningPolicy,
+    signing_pool::{SigningPool, SigningPoolAccess},
     state::{ValidatorRole, ValidatorStateAccess},
 };
 
@@ -25,20 +42,26 @@ where
     KeyManager: key_manager::KeyManager,
 {
     fn bundler_address(&self) -> &str;
-    fn validator_address(&self) -> &str;
-    fn key_manager(&self) -> &KeyManager;
+    fn validator_address(&self) -> String;
+    fn key_manager(&self) -> KeyManager;
+    /// Key manager signing for `validator_address`, if the address is in use
+    fn key_manager_for(&self, validator_address: &str) -> Option<KeyManager>;
     fn current_epoch(&self) -> u128;
     fn current_block(&self) -> u128;
+    fn signing_policy(&self) -> &dyn SigningPolicy;
+    fn readiness(&self) -> &ReadinessConfig;
+    /// Most sign requests accepted in a single batch
+    fn max_batch_size(&self) -> usize;
 }
 
 /// Deserializer from string to u128
-fn de_u128<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+pub(crate) fn de_u128<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
     let s: &str = de::Deserialize::deserialize(deserializer)?;
     s.parse().map_err(de::Error::custom)
 }
 
 /// Serialize as string
-fn ser_as_string<S, T>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+pub(crate) fn ser_as_string<S, T>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
     T: ToString,
@@ -46,7 +69,7 @@ where
     serializer.serialize_str(&val.to_string())
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct SignRequest {
     id: String,
     size: usize,
@@ -59,14 +82,54 @@ pub struct SignRequest {
     signature: String,
 }
 
+/// Promise a slash proposal claims was broken, signed by the bundler
+impl From<&SlashProposal> for SignRequest {
+    fn from(proposal: &SlashProposal) -> Self {
+        Self {
+            id: proposal.id.to_string(),
+            size: proposal.size as usize,
+            fee: proposal.fee as u128,
+            currency: proposal.currency.clone(),
+            block: proposal.block as u128,
+            validator: proposal.validator.clone(),
+            signature: proposal.signature.clone(),
+        }
+    }
+}
+
 impl SignRequest {
-    // FIXME: needs proper error type
-    pub async fn verify<KeyManager>(&self, key_manager: &KeyManager) -> Result<bool, ()>
-    where
-        KeyManager: key_manager::KeyManager,
-    {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn block(&self) -> u128 {
+        self.block
+    }
+
+    pub fn validator(&self) -> &str {
+        &self.validator
+    }
+
+    /// Bundler signature, base64url encoded
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn bundler_signature_data(&self) -> DeepHashChunk {
         // FIXME: fix lifetimes in DeepHashChunk::Chunk and deep_hash to avoid copying the data
-        let signature_data = deep_hash(DeepHashChunk::Chunks(vec![
+        DeepHashChunk::Chunks(vec![
             DeepHashChunk::Chunk(BUNDLR_AS_BUFFER.into()),
             DeepHashChunk::Chunk(ONE_AS_BUFFER.into()),
             DeepHashChunk::Chunk(self.id.as_bytes().to_owned().into()),
@@ -75,12 +138,31 @@ impl SignRequest {
             DeepHashChunk::Chunk(self.currency.as_bytes().to_owned().into()),
             DeepHashChunk::Chunk(self.block.to_string().as_bytes().to_owned().into()),
             DeepHashChunk::Chunk(self.validator.as_bytes().to_owned().into()),
-        ]))
-        .await
-        .map_err(|err| {
-            error!("Failed to build data for signing: {:?}", err);
-        })?;
+        ])
+    }
+
+    fn validator_signature_data(&self, bundler_address: &str) -> DeepHashChunk {
+        DeepHashChunk::Chunks(vec![
+            DeepHashChunk::Chunk(VALIDATOR_AS_BUFFER.into()),
+            DeepHashChunk::Chunk(ONE_AS_BUFFER.into()),
+            DeepHashChunk::Chunk(self.id.as_bytes().to_owned().into()),
+            DeepHashChunk::Chunk(self.size.to_string().as_bytes().to_owned().into()),
+            DeepHashChunk::Chunk(self.fee.to_string().as_bytes().to_owned().into()),
+            DeepHashChunk::Chunk(self.currency.as_bytes().to_owned().into()),
+            DeepHashChunk::Chunk(self.block.to_string().as_bytes().to_owned().into()),
+            DeepHashChunk::Chunk(self.validator.as_bytes().to_owned().into()),
+            DeepHashChunk::Chunk(bundler_address.as_bytes().to_owned().into()),
+        ])
+    }
 
+    fn verify_signature_data<KeyManager>(
+        &self,
+        key_manager: &KeyManager,
+        signature_data: &[u8],
+    ) -> Result<bool, ()>
+    where
+        KeyManager: key_manager::KeyManager,
+    {
         let decoded_signature =
             BASE64URL_NOPAD
                 .decode(self.signature.as_bytes())
@@ -88,32 +170,421 @@ impl SignRequest {
                     error!("Failed to decode signature: {:?}", err);
                 })?;
 
-        Ok(key_manager.verify_bundler_signature(&signature_data, &decoded_signature))
+        Ok(key_manager.verify_bundler_signature(signature_data, &decoded_signature))
     }
 
-    pub async fn sign<KeyManager>(&self, key_manager: &KeyManager) -> Result<String, ()>
+    // FIXME: needs proper error type
+    pub async fn verify<KeyManager>(&self, key_manager: &KeyManager) -> Result<bool, ()>
     where
         KeyManager: key_manager::KeyManager,
     {
-        let bundler_address = key_manager.bundler_address().to_string();
+        let signature_data = deep_hash(self.bundler_signature_data())
+            .await
+            .map_err(|err| {
+                error!("Failed to build data for signing: {:?}", err);
+            })?;
 
-        let signature_data = deep_hash(DeepHashChunk::Chunks(vec![
-            DeepHashChunk::Chunk(VALIDATOR_AS_BUFFER.into()),
-            DeepHashChunk::Chunk(ONE_AS_BUFFER.into()),
-            DeepHashChunk::Chunk(self.id.as_bytes().to_owned().into()),
-            DeepHashChunk::Chunk(self.size.to_string().as_bytes().to_owned().into()),
-            DeepHashChunk::Chunk(self.fee.to_string().as_bytes().to_owned().into()),
-            DeepHashChunk::Chunk(self.currency.as_bytes().to_owned().into()),
-            DeepHashChunk::Chunk(self.block.to_string().as_bytes().to_owned().into()),
-            DeepHashChunk::Chunk(self.validator.as_bytes().to_owned().into()),
-            DeepHashChunk::Chunk(bundler_address.as_bytes().to_owned().into()),
-        ]))
-        .await
-        .map_err(|err| {
+        self.verify_signature_data(key_manager, &signature_data)
+    }
+
+    /// Check bundler signature against any of `keys`, e.g. the keys the
+    /// bundler used around the promised block
+    pub async fn verify_with_keys(&self, keys: &[BundlerPublicKey]) -> Result<bool, ()> {
+        let signature_data = deep_hash(self.bundler_signature_data())
+            .await
+            .map_err(|err| {
+                error!("Failed to build data for signing: {:?}", err);
+            })?;
+        let decoded_signature =
+            BASE64URL_NOPAD
+                .decode(self.signature.as_bytes())
+                .map_err(|err| {
+                    error!("Failed to decode signature: {:?}", err);
+                })?;
+
+        Ok(keys
+            .iter()
+            .any(|key| key.verify(&signature_data, &decoded_signature)))
+    }
+
+    /// Same as [`SignRequest::verify`], but meant to be run on a blocking thread
+    pub fn verify_blocking<KeyManager>(&self, key_manager: &KeyManager) -> Result<bool, ()>
+    where
+        KeyManager: key_manager::KeyManager,
+    {
+        let signature_data = deep_hash_sync(self.bundler_signature_data()).map_err(|err| {
             error!("Failed to build data for signing: {:?}", err);
         })?;
 
-        Ok(BASE64URL_NOPAD.encode(&key_manager.validator_sign(&signature_data)))
+        self.verify_signature_data(key_manager, &signature_data)
+    }
+
+    pub async fn sign<KeyManager>(
+        &self,
+        key_manager: &KeyManager,
+    ) -> Result<String, ValidatorServerError>
+    where
+        KeyManager: key_manager::KeyManager,
+    {
+        let signature_data =
+            deep_hash(self.validator_signature_data(key_manager.bundler_address()))
+                .await
+                .map_err(|err| {
+                    error!("Failed to build data for signing: {:?}", err);
+                    ValidatorServerError::InternalError
+                })?;
+
+        Ok(BASE64URL_NOPAD.encode(&key_manager.validator_sign(&signature_data)?))
+    }
+
+    /// Same as [`SignRequest::sign`], but meant to be run on a blocking thread
+    pub fn sign_blocking<KeyManager>(
+        &self,
+        key_manager: &KeyManager,
+    ) -> Result<String, ValidatorServerError>
+    where
+        KeyManager: key_manager::KeyManager,
+    {
+        let signature_data = self.validator_signature_message(key_manager.bundler_address())?;
+
+        Ok(BASE64URL_NOPAD.encode(&key_manager.validator_sign(&signature_data)?))
+    }
+
+    /// Data the validator signs, meant to be run on a blocking thread
+    pub(crate) fn validator_signature_message(
+        &self,
+        bundler_address: &str,
+    ) -> Result<Vec<u8>, ValidatorServerError> {
+        deep_hash_sync(self.validator_signature_data(bundler_address))
+            .map(|data| data.to_vec())
+            .map_err(|err| {
+                error!("Failed to build data for signing: {:?}", err);
+                ValidatorServerError::InternalError
+            })
+    }
+
+    /// Check if the receipt issued earlier covers exactly this promise, meant
+    /// to be run on a blocking thread
+    pub fn verify_receipt_blocking<KeyManager>(
+        &self,
+        key_manager: &KeyManager,
+        receipt: &str,
+    ) -> Result<bool, ()>
+    where
+        KeyManager: key_manager::KeyManager,
+    {
+        let signature_data = self
+            .validator_signature_message(key_manager.bundler_address())
+            .map_err(|_| ())?;
+
+        let decoded_receipt = BASE64URL_NOPAD.decode(receipt.as_bytes()).map_err(|err| {
+            error!("Failed to decode receipt: {:?}", err);
+        })?;
+
+        Ok(key_manager.verify_validator_signature(&signature_data, &decoded_receipt))
+    }
+
+    fn to_new_transaction(&self, epoch: u128, sig: &str) -> NewTransaction {
+        NewTransaction {
+            id: self.id.clone(),
+            epoch: Epoch(epoch),
+            block_promised: self.block.into(),
+            block_actual: None,
+            signature: sig.as_bytes().to_vec(),
+            validated: false,
+            bundle_id: None,
+            size: Some(self.size as i64),
+            fee: Some(self.fee.to_string()),
+            currency: Some(self.currency.clone()),
+            bundler_signature: Some(self.signature.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// Outcome of receiving a sign request for an already cosigned transaction
+enum Duplicate {
+    /// Same promise as before, here's the receipt issued for it
+    Receipt(String),
+    /// Bundler signed a different promise for the same transaction
+    Equivocation,
+    InvalidSignature,
+}
+
+impl Duplicate {
+    fn into_response(self) -> Result<HttpResponse, ValidatorServerError> {
+        match self {
+            Duplicate::Receipt(sig) => Ok(HttpResponse::Accepted()
+                .insert_header(("Content-Type", "application/octet-stream"))
+                .body(sig.into_bytes())),
+            Duplicate::Equivocation => Err(ValidatorServerError::ConflictingPromise),
+            Duplicate::InvalidSignature => Err(ValidatorServerError::InvalidBundlerSignature),
+        }
+    }
+
+    fn into_result(self, tx_id: String) -> SignResult {
+        match self {
+            Duplicate::Receipt(sig) => SignResult::AlreadySigned {
+                id: tx_id,
+                signature: sig,
+            },
+            Duplicate::Equivocation => {
+                SignResult::rejected(tx_id, ValidatorServerError::ConflictingPromise)
+            }
+            Duplicate::InvalidSignature => {
+                SignResult::rejected(tx_id, ValidatorServerError::InvalidBundlerSignature)
+            }
+        }
+    }
+}
+
+/// Outcome of trying to issue a signature for a verified sign request
+enum Issued {
+    /// Signature issued for the epoch of the promised block
+    Signed { signature: String, epoch: u128 },
+    /// Row for the transaction was created by a concurrent request
+    Existing(Transaction),
+}
+
+/// Issue validator signatures for verified requests
+///
+/// Requests are handled one at a time. A signature is computed first, then
+/// the transaction row and the audit log entry are stored in a short database
+/// transaction. If a concurrent request stored the same transaction in the
+/// meantime, the fresh signature is dropped and the stored row is returned
+/// instead. Signatures are only handed out after commit, so every signature
+/// that leaves this validator is stored exactly once.
+///
+/// Each request is signed with the key manager of the validator address it
+/// was sent to, so requests for the previous key are served during rotation.
+///
+/// Transactions are stored with the epoch of their promised block, as
+/// resolved when the request was admitted.
+///
+/// RSA signatures are computed on the signing pool, no database transaction
+/// is open meanwhile. Requests were already admitted, so this waits for room
+/// in the queue rather than failing.
+fn issue_signatures<KeyManager>(
+    conn: &PgConnection,
+    signing_pool: &SigningPool,
+    requests: Vec<(usize, SignRequest, KeyManager, u128)>,
+) -> Result<Vec<(usize, SignRequest, Issued)>, ValidatorServerError>
+where
+    KeyManager: key_manager::KeyManager + Send + 'static,
+{
+    let mut issued = Vec::with_capacity(requests.len());
+    for (idx, req, key_manager, epoch) in requests {
+        let signature_data = req.validator_signature_message(key_manager.bundler_address())?;
+        let sig = signing_pool
+            .execute_blocking(move || key_manager.validator_sign(&signature_data))??;
+        let sig = BASE64URL_NOPAD.encode(&sig);
+
+        let stored = conn.transaction::<_, ValidatorServerError, _>(|| {
+            let inserted = diesel::insert_into(transactions)
+                .values(&req.to_new_transaction(epoch, &sig))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            if inserted == 0 {
+                return Ok(Some(transactions.find(&req.id).first::<Transaction>(conn)?));
+            }
+
+            audit_log::append(conn, &req, &sig)?;
+            Ok(None)
+        })?;
+
+        let outcome = match stored {
+            Some(stored) => Issued::Existing(stored),
+            None => Issued::Signed {
+                signature: sig,
+                epoch,
+            },
+        };
+        issued.push((idx, req, outcome));
+    }
+
+    Ok(issued)
+}
+
+/// Announce signatures that were just issued
+fn publish_issued<Context>(ctx: &Context, issued: &[(usize, SignRequest, Issued)])
+where
+    Context: EventsAccess,
+{
+    for (_, req, issued) in issued {
+        if let Issued::Signed { epoch, .. } = issued {
+            ctx.events().publish(ValidatorEvent::SignatureIssued {
+                tx_id: req.id.clone(),
+                validator: req.validator.clone(),
+                epoch: *epoch,
+            });
+        }
+    }
+}
+
+/// Tell a repeated request from a conflicting one
+///
+/// Receipt and bundler signature are checked on the signing pool, so this is
+/// turned away as overloaded when the signing queue is full.
+async fn resolve_duplicate<Context, KeyManager>(
+    ctx: &Context,
+    req: &SignRequest,
+    stored: Transaction,
+) -> Result<Duplicate, ValidatorServerError>
+where
+    Context: self::Config<KeyManager> + RuntimeContext + SigningPoolAccess,
+    KeyManager: key_manager::KeyManager + Send + 'static,
+{
+    let receipt = String::from_utf8(stored.signature).map_err(|err| {
+        error!(
+            "Stored signature for {} is not valid UTF-8: {:?}",
+            stored.id, err
+        );
+        ValidatorServerError::InternalError
+    })?;
+
+    // Receipt might have been issued with the previous key
+    let key_manager = ctx
+        .key_manager_for(&req.validator)
+        .unwrap_or_else(|| ctx.key_manager());
+
+    // `None` if the receipt covers this promise, otherwise whether the promise
+    // carries a valid bundler signature. Only bundler signed promises are any
+    // use as evidence.
+    let (receipt, checked) = {
+        let req = req.clone();
+        ctx.signing_pool()
+            .execute(move || {
+                let checked = match req.verify_receipt_blocking(&key_manager, &receipt) {
+                    Ok(true) => Ok(None),
+                    Ok(false) => req.verify_blocking(&key_manager).map(Some),
+                    Err(()) => Err(()),
+                };
+                (receipt, checked)
+            })
+            .await?
+    };
+
+    match checked {
+        Ok(None) => return Ok(Duplicate::Receipt(receipt)),
+        Ok(Some(true)) => (),
+        Ok(Some(false)) => return Ok(Duplicate::InvalidSignature),
+        Err(()) => return Err(ValidatorServerError::InternalError),
+    }
+
+    warn!(
+        "Bundler signed conflicting promise for transaction {}",
+        req.id
+    );
+
+    let evidence = NewEquivocation {
+        tx_id: req.id.clone(),
+        original_signature: receipt.into_bytes(),
+        size: req.size as i64,
+        fee: req.fee.to_string(),
+        currency: req.currency.clone(),
+        block_promised: req.block.into(),
+        bundler_signature: req.signature.as_bytes().to_vec(),
+        original_size: stored.size,
+        original_fee: stored.fee,
+        original_currency: stored.currency,
+        original_block_promised: Some(stored.block_promised),
+        original_bundler_signature: stored.bundler_signature,
+    };
+
+    let conn = ctx.get_db_connection();
+    actix_rt::task::spawn_blocking(move || {
+        diesel::insert_into(equivocations::table)
+            .values(&evidence)
+            .execute(&conn)
+    })
+    .await??;
+
+    Ok(Duplicate::Equivocation)
+}
+
+/// Batch item for an already cosigned transaction
+///
+/// Items that don't fit in the signing queue are rejected as overloaded.
+async fn resolve_duplicate_item<Context, KeyManager>(
+    ctx: &Context,
+    req: SignRequest,
+    stored: Transaction,
+) -> Result<SignResult, ValidatorServerError>
+where
+    Context: self::Config<KeyManager> + RuntimeContext + SigningPoolAccess,
+    KeyManager: key_manager::KeyManager + Send + 'static,
+{
+    match resolve_duplicate::<Context, KeyManager>(ctx, &req, stored).await {
+        Ok(duplicate) => Ok(duplicate.into_result(req.id)),
+        Err(err @ ValidatorServerError::Overloaded { .. }) => Ok(SignResult::rejected(req.id, err)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Epoch the promised block falls in, if the validator cosigns in it
+///
+/// Leader of an epoch cosigns as well.
+fn cosigned_epoch_at<Context>(ctx: &Context, block: u128) -> Option<u128>
+where
+    Context: ValidatorStateAccess,
+{
+    ctx.get_validator_state()
+        .epoch_at(block)
+        .filter(|epoch| matches!(epoch.role, ValidatorRole::Cosigner | ValidatorRole::Leader))
+        .map(|epoch| epoch.seq)
+}
+
+/// Checks that can be done without touching the signature or the database
+///
+/// Returns key manager that should sign the request.
+fn check_request<Context, KeyManager>(
+    ctx: &Context,
+    req: &SignRequest,
+) -> Result<KeyManager, ValidatorServerError>
+where
+    Context: self::Config<KeyManager>,
+    KeyManager: key_manager::KeyManager,
+{
+    let key_manager = ctx
+        .key_manager_for(&req.validator)
+        .ok_or(ValidatorServerError::InvalidValidatorAddress)?;
+
+    ctx.signing_policy().check(req, ctx.current_block())?;
+
+    Ok(key_manager)
+}
+
+/// Outcome of a single item in a batch sign request
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SignResult {
+    Signed {
+        id: String,
+        signature: String,
+    },
+    AlreadySigned {
+        id: String,
+        signature: String,
+    },
+    /// Carries the same code, message and details as the error envelope
+    Rejected {
+        id: String,
+        code: ErrorCode,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        details: Option<serde_json::Value>,
+    },
+}
+
+impl SignResult {
+    fn rejected(tx_id: String, err: ValidatorServerError) -> Self {
+        let body = err.error_body();
+        SignResult::Rejected {
+            id: tx_id,
+            code: body.code,
+            message: body.message,
+            details: body.details,
+        }
     }
 }
 
@@ -122,106 +593,210 @@ pub async fn sign_route<Context, KeyManager>(
     body: Json<SignRequest>,
 ) -> actix_web::Result<HttpResponse, ValidatorServerError>
 where
-    Context: self::Config<KeyManager> + RuntimeContext + Send,
-    KeyManager: key_manager::KeyManager,
+    Context: self::Config<KeyManager> + RuntimeContext + EventsAccess + SigningPoolAccess + Send,
+    KeyManager: key_manager::KeyManager + Clone + Send + 'static,
 {
-    // FIXME: checking role should be a function of block height
-    // we probably need to track role for previous, current and next epochs
-    // to allow bundler node and this validator to see new blocks at different
-    // times
-    if ctx.get_validator_state().role() != ValidatorRole::Cosigner {
-        return Ok(HttpResponse::BadRequest().finish());
-    }
-
     let body = body.into_inner();
 
-    // Verify
-    let exists = {
+    // Role and block height might be outdated, e.g. right after start up
+    check_readiness(ctx.get_validator_state(), ctx.readiness())?;
+
+    // Role is resolved by the promised block so that bundler and validator
+    // seeing new blocks at different times agree on the epoch
+    let epoch =
+        cosigned_epoch_at(ctx.get_ref(), body.block).ok_or(ValidatorServerError::NotCosigner)?;
+
+    // Same transaction was already cosigned, hand out the receipt issued for it
+    let stored = {
         let conn = ctx.get_db_connection();
         let filter = id.eq(body.id.clone());
         actix_rt::task::spawn_blocking(move || {
-            match transactions.filter(filter).count().get_result(&conn) {
-                Ok(0) => Ok(false),
-                Ok(_) => Ok(true),
-                Err(err) => Err(err),
-            }
+            transactions
+                .filter(filter)
+                .first::<Transaction>(&conn)
+                .optional()
         })
+        .await??
     };
 
-    if let Ok(true) = exists
-        .await
-        .map_err(|_| ValidatorServerError::InternalError)?
-    {
-        return Ok(HttpResponse::Accepted().finish());
+    if let Some(stored) = stored {
+        return resolve_duplicate::<Context, KeyManager>(ctx.get_ref(), &body, stored)
+            .await?
+            .into_response();
     }
 
-    let current_block = ctx.current_block();
-    let key_manager = ctx.key_manager();
-
-    if body.validator != *ctx.validator_address() {
-        return Ok(HttpResponse::BadRequest().body("Invalid validator address"));
-    }
+    // Run admission checks before spending time on the signature
+    let key_manager = check_request::<Context, KeyManager>(ctx.get_ref(), &body)?;
 
-    // Check that the body.block is not too far in the past nor too far in the future
-    match body.block.cmp(&current_block) {
-        std::cmp::Ordering::Less => {
-            return Ok(HttpResponse::BadRequest().body("Invalid block number"))
-        }
-        std::cmp::Ordering::Greater if body.block - current_block < 395 => {
-            return Ok(HttpResponse::BadRequest().body("Invalid block number"))
-        }
-        std::cmp::Ordering::Greater if body.block - current_block > 405 => {
-            return Ok(HttpResponse::BadRequest().body("Invalid block number"))
-        }
-        _ => (),
-    }
+    // Turned away with 503 when the signing queue is full
+    let (body, key_manager, valid) = ctx
+        .signing_pool()
+        .execute(move || {
+            let valid = body.verify_blocking(&key_manager);
+            (body, key_manager, valid)
+        })
+        .await?;
 
-    match body.verify(key_manager).await {
+    match valid {
         Ok(true) => (),
-        Ok(false) => return Ok(HttpResponse::BadRequest().body("Invalid bundler signature")),
-        Err(()) => return Err(ValidatorServerError::InternalError),
-    };
-
-    // Sign
-    let sig = match body.sign(key_manager).await {
-        Ok(sig) => sig,
+        Ok(false) => return Err(ValidatorServerError::InvalidBundlerSignature),
         Err(()) => return Err(ValidatorServerError::InternalError),
     };
 
-    // Add to db
-    let current_epoch = ctx.current_epoch();
-
-    let new_transaction = NewTransaction {
-        id: body.id,
-        epoch: Epoch(current_epoch),
-        block_promised: body.block.into(),
-        block_actual: None,
-        signature: sig.as_bytes().to_vec(),
-        validated: false,
-        bundle_id: None,
-    };
-
+    // Sign and store
     let conn = ctx.get_db_connection();
-    actix_rt::task::spawn_blocking(move || {
-        diesel::insert_into(transactions)
-            .values::<NewTransaction>(new_transaction)
-            .execute(&conn)
+    let signing_pool = ctx.signing_pool().clone();
+    let mut issued = actix_rt::task::spawn_blocking(move || {
+        issue_signatures(&conn, &signing_pool, vec![(0, body, key_manager, epoch)])
     })
     .await??;
+    publish_issued(ctx.get_ref(), &issued);
+
+    match issued.pop() {
+        Some((_, _, Issued::Signed { signature: sig, .. })) => Ok(HttpResponse::Ok()
+            .insert_header(("Content-Type", "application/octet-stream"))
+            .body(sig.into_bytes())),
+        Some((_, body, Issued::Existing(stored))) => {
+            resolve_duplicate::<Context, KeyManager>(ctx.get_ref(), &body, stored)
+                .await?
+                .into_response()
+        }
+        None => Err(ValidatorServerError::InternalError),
+    }
+}
+
+pub async fn sign_batch_route<Context, KeyManager>(
+    ctx: Data<Context>,
+    body: Json<Vec<SignRequest>>,
+) -> actix_web::Result<HttpResponse, ValidatorServerError>
+where
+    Context: self::Config<KeyManager> + RuntimeContext + EventsAccess + SigningPoolAccess + Send,
+    KeyManager: key_manager::KeyManager + Clone + Send + 'static,
+{
+    let requests = body.into_inner();
+
+    // Every item may take an RSA verification and signature
+    if requests.len() > ctx.max_batch_size() {
+        return Err(ValidatorServerError::BatchTooLarge {
+            max: ctx.max_batch_size(),
+        });
+    }
+
+    check_readiness(ctx.get_validator_state(), ctx.readiness())?;
+
+    let mut existing: HashMap<String, Transaction> = {
+        let conn = ctx.get_db_connection();
+        let ids: Vec<String> = requests.iter().map(|req| req.id.clone()).collect();
+        actix_rt::task::spawn_blocking(move || {
+            transactions
+                .filter(id.eq_any(ids))
+                .load::<Transaction>(&conn)
+        })
+        .await??
+        .into_iter()
+        .map(|tx| (tx.id.clone(), tx))
+        .collect()
+    };
+
+    let mut results: Vec<Option<SignResult>> = Vec::with_capacity(requests.len());
+    let mut pending = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut seen = HashSet::new();
+
+    for req in requests {
+        let result = if !seen.insert(req.id.clone()) {
+            Some(SignResult::rejected(
+                req.id,
+                ValidatorServerError::DuplicateTransaction,
+            ))
+        } else if let Some(epoch) = cosigned_epoch_at(ctx.get_ref(), req.block) {
+            if let Some(stored) = existing.remove(&req.id) {
+                duplicates.push((results.len(), req, stored));
+                None
+            } else {
+                match check_request::<Context, KeyManager>(ctx.get_ref(), &req) {
+                    Ok(key_manager) => {
+                        pending.push((results.len(), req, key_manager, epoch));
+                        None
+                    }
+                    Err(err) => Some(SignResult::rejected(req.id, err)),
+                }
+            }
+        } else {
+            Some(SignResult::rejected(
+                req.id,
+                ValidatorServerError::NotCosigner,
+            ))
+        };
+        results.push(result);
+    }
+
+    for (idx, req, stored) in duplicates {
+        results[idx] =
+            Some(resolve_duplicate_item::<Context, KeyManager>(ctx.get_ref(), req, stored).await?);
+    }
+
+    // Verify bundler signatures in parallel on the signing pool, items that
+    // don't fit in the queue are rejected as overloaded
+    let mut verifying = Vec::with_capacity(pending.len());
+    for (idx, req, key_manager, epoch) in pending {
+        let tx_id = req.id.clone();
+        match ctx.signing_pool().submit(move || {
+            let valid = req.verify_blocking(&key_manager);
+            (idx, req, key_manager, epoch, valid)
+        }) {
+            Ok(verification) => verifying.push(verification),
+            Err(err) => results[idx] = Some(SignResult::rejected(tx_id, err.into())),
+        }
+    }
+    let verified = join_all(verifying).await;
+
+    let mut accepted = Vec::new();
+    for res in verified {
+        match res.map_err(|_| ValidatorServerError::InternalError)? {
+            (idx, req, key_manager, epoch, Ok(true)) => {
+                accepted.push((idx, req, key_manager, epoch))
+            }
+            (idx, req, _, _, Ok(false)) => {
+                results[idx] = Some(SignResult::rejected(
+                    req.id,
+                    ValidatorServerError::InvalidBundlerSignature,
+                ))
+            }
+            (_, _, _, _, Err(())) => return Err(ValidatorServerError::InternalError),
+        }
+    }
+
+    // Sign and store all accepted transactions at once
+    if !accepted.is_empty() {
+        let conn = ctx.get_db_connection();
+        let signing_pool = ctx.signing_pool().clone();
+        let issued =
+            actix_rt::task::spawn_blocking(move || issue_signatures(&conn, &signing_pool, accepted))
+                .await??;
+        publish_issued(ctx.get_ref(), &issued);
+
+        for (idx, req, issued) in issued {
+            results[idx] = Some(match issued {
+                Issued::Signed { signature: sig, .. } => SignResult::Signed {
+                    id: req.id,
+                    signature: sig,
+                },
+                Issued::Existing(stored) => {
+                    resolve_duplicate_item::<Context, KeyManager>(ctx.get_ref(), req, stored)
+                        .await?
+                }
+            });
+        }
+    }
 
-    Ok(HttpResponse::Ok()
-        .insert_header(("Content-Type", "application/octet-stream"))
-        .body(sig.as_bytes().to_vec()))
+    let results: Vec<SignResult> = results.into_iter().flatten().collect();
+
+    Ok(HttpResponse::Ok().json(results))
 }
 
 #[cfg(test)]
-mod tests {
-    use actix_web::{
-        http::header::ContentType,
-        test::{call_service, init_service, TestRequest},
-        web::{self, Data},
-        App,
-    };
+pub mod test_utils {
     use bundlr_sdk::{
         deep_hash::{DeepHashChunk, ONE_AS_BUFFER},
         deep_hash_sync::deep_hash_sync,
@@ -233,19 +808,13 @@ mod tests {
         rsa::Padding,
         sign,
     };
-    use reqwest::StatusCode;
 
-    use crate::{
-        consts::{BUNDLR_AS_BUFFER, VALIDATOR_AS_BUFFER},
-        context::{test_utils::test_context, AppContext},
-        http::reqwest::mock::MockHttpClient,
-        key_manager::{test_utils::test_keys, KeyManager},
-        server::routes::sign::{sign_route, Config},
-        state::ValidatorStateAccess,
-    };
+    use crate::consts::BUNDLR_AS_BUFFER;
 
     use super::SignRequest;
-    fn test_message(
+
+    /// Sign request for `tx` signed by the bundler
+    pub fn test_message(
         signing_key: &PKey<Private>,
         block: u128,
         validator: String,
@@ -287,13 +856,55 @@ mod tests {
             signature: sig,
         }
     }
+}
 
-    #[actix_rt::test]
-    async fn verify_unsigned_body() {
-        let (key_manager, bundler_private_key) = test_keys();
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::header::{self, ContentType},
+        test::{call_service, init_service, read_body, read_body_json, TestRequest},
+        web::{self, Data},
+        App,
+    };
+    use bundlr_sdk::{
+        deep_hash::{DeepHashChunk, ONE_AS_BUFFER},
+        deep_hash_sync::deep_hash_sync,
+    };
+    use data_encoding::BASE64URL_NOPAD;
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    use reqwest::StatusCode;
+    use std::{sync::mpsc, time::Duration};
 
-        let msg = test_message(
-            &bundler_private_key,
+    use crate::{
+        consts::VALIDATOR_AS_BUFFER,
+        context::{
+            test_utils::{test_context, test_context_with_signing_pool},
+            AppContext,
+        },
+        database::{
+            models::{Block, Epoch, Equivocation, Transaction},
+            schema::{equivocations, transactions},
+        },
+        events::{EventsAccess, ValidatorEvent},
+        http::reqwest::mock::MockHttpClient,
+        key_manager::{test_utils::test_keys, KeyManager},
+        key_rotation::KeyRotationAccess,
+        readiness::ReadinessConfig,
+        server::error::{ErrorBody, ErrorCode},
+        server::routes::sign::{sign_batch_route, sign_route, Config},
+        server::RuntimeContext,
+        signing_pool::{SigningPool, SigningPoolAccess},
+        state::{ScheduledEpoch, ValidatorRole, ValidatorStateAccess},
+    };
+
+    use super::{test_utils::test_message, SignResult};
+
+    #[actix_rt::test]
+    async fn verify_unsigned_body() {
+        let (key_manager, bundler_private_key) = test_keys();
+
+        let msg = test_message(
+            &bundler_private_key,
             500,
             key_manager.validator_address().to_string(),
             "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Z1",
@@ -371,6 +982,61 @@ mod tests {
         );
     }
 
+    #[actix_web::test]
+    async fn previous_validator_address_is_signed_only_during_overlap() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let old_address = key_manager.validator_address().to_string();
+        let ctx = test_context(key_manager);
+
+        let (new_keys, _) = crate::key_manager::test_utils::test_keys();
+        ctx.key_rotation().rotate(new_keys, Duration::from_secs(60));
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            old_address.clone(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-R1",
+        );
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(msg)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let (newest_keys, _) = crate::key_manager::test_utils::test_keys();
+        ctx.key_rotation().rotate(newest_keys, Duration::ZERO);
+
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            old_address,
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-R2",
+        );
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(msg)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::InvalidValidatorAddress);
+    }
+
     #[actix_web::test]
     async fn block_number_too_far_ahead_yields_bad_request() {
         let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
@@ -428,6 +1094,17 @@ mod tests {
 
         let res = call_service(&app, req).await;
         assert_eq!(res.status(), StatusCode::BAD_REQUEST,);
+
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::PolicyViolation);
+        assert_eq!(
+            body.details,
+            Some(serde_json::json!({
+                "rule": "promise_window",
+                "min_block": "401",
+                "max_block": "411",
+            }))
+        );
     }
 
     #[actix_web::test]
@@ -494,4 +1171,550 @@ mod tests {
         let res = call_service(&app, req).await;
         assert_eq!(res.status(), StatusCode::BAD_REQUEST,);
     }
+
+    #[actix_web::test]
+    async fn batch_of_valid_sign_requests_returns_signature_for_each_item() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_batch_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let validator_address = ctx.key_manager().validator_address().to_string();
+        let msgs = vec![
+            test_message(
+                &bundler_private_key,
+                400,
+                validator_address.clone(),
+                "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B1",
+            ),
+            test_message(
+                &bundler_private_key,
+                400,
+                validator_address,
+                "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B2",
+            ),
+        ];
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(msgs)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let results: Vec<SignResult> = read_body_json(res).await;
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|res| matches!(res, SignResult::Signed { .. })));
+    }
+
+    #[actix_web::test]
+    async fn batch_sign_request_reports_result_per_item() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_batch_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let validator_address = ctx.key_manager().validator_address().to_string();
+        let (_, wrong_key) = crate::key_manager::test_utils::test_keys();
+        let msgs = vec![
+            test_message(
+                &bundler_private_key,
+                400,
+                validator_address.clone(),
+                "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B3",
+            ),
+            test_message(
+                &wrong_key,
+                400,
+                validator_address.clone(),
+                "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B4",
+            ),
+            test_message(
+                &bundler_private_key,
+                406,
+                validator_address,
+                "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B5",
+            ),
+        ];
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msgs)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let results: Vec<SignResult> = read_body_json(res).await;
+        assert!(matches!(results[0], SignResult::Signed { .. }));
+        assert_eq!(
+            results[1],
+            SignResult::Rejected {
+                id: "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B4".to_string(),
+                code: ErrorCode::InvalidBundlerSignature,
+                message: "Invalid bundler signature".to_string(),
+                details: None,
+            }
+        );
+        assert_eq!(
+            results[2],
+            SignResult::Rejected {
+                id: "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B5".to_string(),
+                code: ErrorCode::PolicyViolation,
+                message: "Invalid block number".to_string(),
+                details: Some(serde_json::json!({
+                    "rule": "promise_window",
+                    "min_block": "395",
+                    "max_block": "405",
+                })),
+            }
+        );
+
+        // Sending the same batch again yields already signed for the stored item
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msgs)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        let repeated: Vec<SignResult> = read_body_json(res).await;
+        match (&results[0], &repeated[0]) {
+            (
+                SignResult::Signed { signature, .. },
+                SignResult::AlreadySigned {
+                    signature: receipt, ..
+                },
+            ) => assert_eq!(signature, receipt),
+            other => panic!("Unexpected results: {:?}", other),
+        }
+    }
+
+    #[actix_web::test]
+    async fn batch_over_maximum_size_yields_bad_request() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager).with_max_batch_size(1);
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_batch_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let validator_address = ctx.key_manager().validator_address().to_string();
+        let msgs = vec![
+            test_message(
+                &bundler_private_key,
+                400,
+                validator_address.clone(),
+                "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B6",
+            ),
+            test_message(
+                &bundler_private_key,
+                400,
+                validator_address,
+                "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-B7",
+            ),
+        ];
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(msgs)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::BatchTooLarge);
+        assert_eq!(body.details, Some(serde_json::json!({ "max_batch_size": 1 })));
+    }
+
+    #[actix_web::test]
+    async fn sign_request_for_block_in_idle_epoch_yields_bad_request() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+        ctx.get_validator_state().schedule_epoch(ScheduledEpoch {
+            seq: 1,
+            height: 300,
+            role: ValidatorRole::Idle,
+        });
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            ctx.key_manager().validator_address().to_string(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Z8",
+        );
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(msg)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn transaction_is_stored_with_epoch_of_promised_block() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+        // Validator hasn't seen the epoch activate yet
+        ctx.get_validator_state().schedule_epoch(ScheduledEpoch {
+            seq: 1,
+            height: 300,
+            role: ValidatorRole::Cosigner,
+        });
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            ctx.key_manager().validator_address().to_string(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Q1",
+        );
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(msg)
+            .to_request();
+
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let stored = transactions::table
+            .find("dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Q1")
+            .first::<Transaction>(&ctx.get_db_connection())
+            .unwrap();
+        assert_eq!(stored.epoch, Epoch(1));
+        assert_eq!(ctx.current_epoch(), 0);
+    }
+
+    #[actix_web::test]
+    async fn repeated_sign_request_returns_previously_issued_signature() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            ctx.key_manager().validator_address().to_string(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-D1",
+        );
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let sig = read_body(res).await;
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(read_body(res).await, sig);
+    }
+
+    #[actix_web::test]
+    async fn concurrent_sign_requests_issue_single_signature() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let tx_id = "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-E1";
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            ctx.key_manager().validator_address().to_string(),
+            tx_id,
+        );
+
+        let requests = (0..2).map(|_| {
+            let req = TestRequest::post()
+                .uri("/")
+                .insert_header(ContentType::json())
+                .set_json(&msg)
+                .to_request();
+            call_service(&app, req)
+        });
+        let responses = futures::future::join_all(requests).await;
+
+        let mut statuses = Vec::new();
+        let mut bodies = Vec::new();
+        for res in responses {
+            statuses.push(res.status());
+            bodies.push(read_body(res).await);
+        }
+        statuses.sort();
+        assert_eq!(statuses, vec![StatusCode::OK, StatusCode::ACCEPTED]);
+        assert_eq!(bodies[0], bodies[1]);
+
+        let conn = ctx.get_db_connection();
+        let stored = transactions::table
+            .find(tx_id)
+            .first::<Transaction>(&conn)
+            .unwrap();
+        assert_eq!(stored.signature, bodies[0].to_vec());
+    }
+
+    #[actix_web::test]
+    async fn conflicting_sign_request_for_signed_transaction_yields_conflict() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let tx_id = "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-D2";
+        let validator_address = ctx.key_manager().validator_address().to_string();
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(test_message(
+                &bundler_private_key,
+                400,
+                validator_address.clone(),
+                tx_id,
+            ))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(test_message(
+                &bundler_private_key,
+                401,
+                validator_address,
+                tx_id,
+            ))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::ConflictingPromise);
+
+        let conn = ctx.get_db_connection();
+        let evidence = equivocations::table
+            .filter(equivocations::tx_id.eq(tx_id))
+            .load::<Equivocation>(&conn)
+            .unwrap();
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].block_promised, Block(401));
+    }
+
+    #[actix_web::test]
+    async fn sign_request_is_turned_away_when_signing_queue_is_full() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context_with_signing_pool(
+            key_manager,
+            SigningPool::new(1, 1, Duration::from_secs(2)),
+        );
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        // Occupy the only worker, then fill the queue
+        let (release, blocked) = mpsc::channel::<()>();
+        let (started, running) = mpsc::channel();
+        let busy = ctx
+            .signing_pool()
+            .submit(move || {
+                started.send(()).unwrap();
+                blocked.recv().unwrap();
+            })
+            .unwrap();
+        running.recv_timeout(Duration::from_secs(5)).unwrap();
+        let queued = ctx.signing_pool().submit(|| ()).unwrap();
+
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            ctx.key_manager().validator_address().to_string(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-O1",
+        );
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "2");
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::Overloaded);
+        assert_eq!(ctx.signing_pool().stats().rejected, 1);
+
+        release.send(()).unwrap();
+        busy.await.unwrap();
+        queued.await.unwrap();
+
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn issued_signature_is_published() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager);
+        let mut events = ctx.events().subscribe();
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        let validator = ctx.key_manager().validator_address().to_string();
+        let msg = test_message(
+            &bundler_private_key,
+            400,
+            validator.clone(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-P1",
+        );
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        match events.try_recv().unwrap() {
+            ValidatorEvent::SignatureIssued {
+                tx_id,
+                validator: signed_for,
+                epoch,
+            } => {
+                assert_eq!(tx_id, "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-P1");
+                assert_eq!(signed_for, validator);
+                assert_eq!(epoch, ctx.current_epoch());
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        // Handing out the stored receipt again is not a new signature
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[actix_web::test]
+    async fn sign_request_is_refused_while_contract_state_is_behind() {
+        let (key_manager, bundler_private_key) = crate::key_manager::test_utils::test_keys();
+        let ctx = test_context(key_manager).with_readiness(ReadinessConfig::default());
+
+        let app = App::new().app_data(Data::new(ctx.clone())).route(
+            "/",
+            web::post().to(sign_route::<AppContext<MockHttpClient>, _>),
+        );
+
+        let app = init_service(app).await;
+
+        // Network moved on while contract state was not synced
+        let state = ctx.get_validator_state();
+        state.set_current_block(100);
+        state.mark_network_synced();
+
+        let msg = test_message(
+            &bundler_private_key,
+            500,
+            ctx.key_manager().validator_address().to_string(),
+            "dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-N1",
+        );
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: ErrorBody = read_body_json(res).await;
+        assert_eq!(body.code, ErrorCode::NotReady);
+        assert_eq!(
+            body.message,
+            "Validator is not ready: contract state was last synced 100 blocks ago"
+        );
+
+        state.mark_contract_synced();
+        let req = TestRequest::post()
+            .uri("/")
+            .insert_header(ContentType::json())
+            .set_json(&msg)
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
 }
@@ -10,20 +10,24 @@ use crate::database::schema::transactions::{self, id};
 use crate::server::RuntimeContext;
 use crate::{server::error::ValidatorServerError, key_manager};
 use crate::server::routes::sign::Config;
+use crate::signing_pool::{SigningPoolAccess, SigningPoolStats};
 
 #[derive(Serialize)]
 struct StatusBody {
     total_txs: i64,
     epoch: u128,
     next_epoch: u128,
-    previous_epoch: u128
+    previous_epoch: u128,
+    /// Not synced with the contract and the network since start up
+    stale: bool,
+    signing: SigningPoolStats,
 }
 
 pub async fn status<Context, KeyManager>(
     ctx: Data<Context>,
 ) -> actix_web::Result<HttpResponse, ValidatorServerError>
 where
-    Context: self::Config<KeyManager> + RuntimeContext,
+    Context: self::Config<KeyManager> + RuntimeContext + SigningPoolAccess,
     KeyManager: key_manager::KeyManager,
 {
     let conn = ctx.get_db_connection();
@@ -37,7 +41,9 @@ where
         total_txs,
         epoch: current_epoch,
         next_epoch: current_epoch + 1,
-        previous_epoch: current_epoch - 1
+        previous_epoch: current_epoch - 1,
+        stale: ctx.get_validator_state().is_stale(),
+        signing: ctx.signing_pool().stats(),
     };
     
     Ok(HttpResponse::Ok().json(body))
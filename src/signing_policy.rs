@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+
+use crate::server::routes::sign::SignRequest;
+
+/// Machine readable identifier of the rule that rejected a sign request
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRule {
+    PromiseWindow,
+    MaxSize,
+    AllowedCurrency,
+    MinFee,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct PolicyViolation {
+    pub rule: PolicyRule,
+    pub reason: String,
+    /// Rule specific context, e.g. the accepted range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl PolicyViolation {
+    fn new(rule: PolicyRule, reason: impl Into<String>) -> Self {
+        Self {
+            rule,
+            reason: reason.into(),
+            details: None,
+        }
+    }
+
+    fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Admission rule run against every sign request before its signature is verified
+pub trait SigningPolicy: Send + Sync {
+    fn check(&self, req: &SignRequest, current_block: u128) -> Result<(), PolicyViolation>;
+}
+
+/// Promised block must be within `min_blocks..=max_blocks` from the current block
+pub struct PromiseWindow {
+    pub min_blocks: u128,
+    pub max_blocks: u128,
+}
+
+impl SigningPolicy for PromiseWindow {
+    fn check(&self, req: &SignRequest, current_block: u128) -> Result<(), PolicyViolation> {
+        let block = req.block();
+        if block < current_block
+            || block - current_block < self.min_blocks
+            || block - current_block > self.max_blocks
+        {
+            return Err(
+                PolicyViolation::new(PolicyRule::PromiseWindow, "Invalid block number")
+                    .with_details(json!({
+                        "min_block": (current_block + self.min_blocks).to_string(),
+                        "max_block": (current_block + self.max_blocks).to_string(),
+                    })),
+            );
+        }
+        Ok(())
+    }
+}
+
+pub struct MaxSize(pub usize);
+
+impl SigningPolicy for MaxSize {
+    fn check(&self, req: &SignRequest, _current_block: u128) -> Result<(), PolicyViolation> {
+        if req.size() > self.0 {
+            return Err(PolicyViolation::new(
+                PolicyRule::MaxSize,
+                format!("Size exceeds maximum of {} bytes", self.0),
+            )
+            .with_details(json!({ "max_size": self.0 })));
+        }
+        Ok(())
+    }
+}
+
+pub struct AllowedCurrencies(pub HashSet<String>);
+
+impl SigningPolicy for AllowedCurrencies {
+    fn check(&self, req: &SignRequest, _current_block: u128) -> Result<(), PolicyViolation> {
+        if !self.0.contains(req.currency()) {
+            return Err(PolicyViolation::new(
+                PolicyRule::AllowedCurrency,
+                format!("Currency {} is not allowed", req.currency()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub struct MinFee(pub u128);
+
+impl SigningPolicy for MinFee {
+    fn check(&self, req: &SignRequest, _current_block: u128) -> Result<(), PolicyViolation> {
+        if req.fee() < self.0 {
+            return Err(PolicyViolation::new(
+                PolicyRule::MinFee,
+                format!("Fee is below minimum of {}", self.0),
+            )
+            .with_details(json!({ "min_fee": self.0.to_string() })));
+        }
+        Ok(())
+    }
+}
+
+/// Set of rules, request is rejected by the first rule that fails
+#[derive(Default)]
+pub struct SigningPolicies(Vec<Box<dyn SigningPolicy>>);
+
+impl SigningPolicies {
+    pub fn with(mut self, rule: impl SigningPolicy + 'static) -> Self {
+        self.0.push(Box::new(rule));
+        self
+    }
+}
+
+impl SigningPolicy for SigningPolicies {
+    fn check(&self, req: &SignRequest, current_block: u128) -> Result<(), PolicyViolation> {
+        self.0
+            .iter()
+            .try_for_each(|rule| rule.check(req, current_block))
+    }
+}
+
+/// Deserializer from optional number or string to u128
+fn de_optional_u128<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u128>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(n) => Ok(Some(n.into())),
+        NumberOrString::String(s) => s.parse().map(Some).map_err(de::Error::custom),
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "RawPromiseWindowConfig")]
+pub struct PromiseWindowConfig {
+    pub min_blocks: u64,
+    pub max_blocks: u64,
+}
+
+/// Promise window as written in the policy file, before it's checked
+#[derive(Deserialize)]
+struct RawPromiseWindowConfig {
+    min_blocks: u64,
+    max_blocks: u64,
+}
+
+impl TryFrom<RawPromiseWindowConfig> for PromiseWindowConfig {
+    type Error = String;
+
+    fn try_from(raw: RawPromiseWindowConfig) -> Result<Self, Self::Error> {
+        if raw.min_blocks > raw.max_blocks {
+            return Err(format!(
+                "promise_window min_blocks {} is greater than max_blocks {}",
+                raw.min_blocks, raw.max_blocks
+            ));
+        }
+        Ok(Self {
+            min_blocks: raw.min_blocks,
+            max_blocks: raw.max_blocks,
+        })
+    }
+}
+
+impl Default for PromiseWindowConfig {
+    fn default() -> Self {
+        Self {
+            min_blocks: 395,
+            max_blocks: 405,
+        }
+    }
+}
+
+/// Signing policy configuration as read from the policy file
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct SigningPolicyConfig {
+    #[serde(default)]
+    pub promise_window: PromiseWindowConfig,
+    #[serde(default)]
+    pub max_size: Option<usize>,
+    #[serde(default)]
+    pub allowed_currencies: Option<Vec<String>>,
+    #[serde(deserialize_with = "de_optional_u128", default)]
+    pub min_fee: Option<u128>,
+}
+
+impl From<SigningPolicyConfig> for SigningPolicies {
+    fn from(config: SigningPolicyConfig) -> Self {
+        let mut policies = SigningPolicies::default().with(PromiseWindow {
+            min_blocks: config.promise_window.min_blocks.into(),
+            max_blocks: config.promise_window.max_blocks.into(),
+        });
+
+        if let Some(max_size) = config.max_size {
+            policies = policies.with(MaxSize(max_size));
+        }
+
+        if let Some(currencies) = config.allowed_currencies {
+            policies = policies.with(AllowedCurrencies(currencies.into_iter().collect()));
+        }
+
+        if let Some(min_fee) = config.min_fee {
+            policies = policies.with(MinFee(min_fee));
+        }
+
+        policies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::routes::sign::SignRequest;
+
+    use super::{PolicyRule, SigningPolicies, SigningPolicy, SigningPolicyConfig};
+
+    fn request(size: usize, fee: u128, currency: &str, block: u128) -> SignRequest {
+        let body = format!(
+            r#"{{"id":"dtdOmHZMOtGb2C0zLqLBUABrONDZ5rzRh9NengT1-Z1","size":{},"fee":"{}","currency":"{}","block":"{}","validator":"validator","signature":"signature"}}"#,
+            size, fee, currency, block
+        );
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[test]
+    fn default_config_only_checks_promise_window() {
+        let policies = SigningPolicies::from(SigningPolicyConfig::default());
+
+        assert!(policies.check(&request(1, 0, "FOO", 400), 0).is_ok());
+        let violation = policies.check(&request(1, 0, "FOO", 406), 0).unwrap_err();
+        assert_eq!(violation.rule, PolicyRule::PromiseWindow);
+        assert_eq!(
+            violation.details,
+            Some(serde_json::json!({ "min_block": "395", "max_block": "405" }))
+        );
+        assert_eq!(
+            policies
+                .check(&request(1, 0, "FOO", 400), 6)
+                .unwrap_err()
+                .rule,
+            PolicyRule::PromiseWindow
+        );
+    }
+
+    #[test]
+    fn parse_config_with_all_rules() {
+        let config: SigningPolicyConfig = serde_json::from_str(
+            r#"{"promise_window":{"min_blocks":10,"max_blocks":20},"max_size":100,"allowed_currencies":["arweave"],"min_fee":"5"}"#,
+        )
+        .unwrap();
+        let policies = SigningPolicies::from(config);
+
+        assert!(policies.check(&request(100, 5, "arweave", 15), 0).is_ok());
+        assert_eq!(
+            policies
+                .check(&request(101, 5, "arweave", 15), 0)
+                .unwrap_err()
+                .rule,
+            PolicyRule::MaxSize
+        );
+        assert_eq!(
+            policies
+                .check(&request(100, 5, "solana", 15), 0)
+                .unwrap_err()
+                .rule,
+            PolicyRule::AllowedCurrency
+        );
+        assert_eq!(
+            policies
+                .check(&request(100, 4, "arweave", 15), 0)
+                .unwrap_err()
+                .rule,
+            PolicyRule::MinFee
+        );
+    }
+
+    #[test]
+    fn min_fee_can_be_a_number() {
+        let config: SigningPolicyConfig = serde_json::from_str(r#"{"min_fee":1000}"#).unwrap();
+        assert_eq!(config.min_fee, Some(1000));
+
+        let config: SigningPolicyConfig = serde_json::from_str(r#"{"min_fee":"1000"}"#).unwrap();
+        assert_eq!(config.min_fee, Some(1000));
+    }
+
+    #[test]
+    fn inverted_promise_window_is_rejected() {
+        let err = serde_json::from_str::<SigningPolicyConfig>(
+            r#"{"promise_window":{"min_blocks":20,"max_blocks":10}}"#,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("min_blocks 20 is greater than max_blocks 10"));
+    }
+}
@@ -0,0 +1,314 @@
+//! Dedicated threads for RSA signing and signature verification
+//!
+//! Signing with a 4096 bit key takes milliseconds, running it on the actix
+//! workers stalls every other request handled by the same worker. Jobs are
+//! queued for a fixed number of threads instead. The queue is bounded, when
+//! it's full new sign requests are turned away so that the bundler backs off
+//! rather than piling up requests with ever growing latency.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, warn};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Error)]
+pub enum SigningPoolError {
+    #[error("signing queue is full")]
+    Busy { retry_after: Duration },
+    #[error("signing job failed")]
+    Failed,
+}
+
+pub trait SigningPoolAccess {
+    fn signing_pool(&self) -> &SigningPool;
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    running: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+    wait_micros: AtomicU64,
+    run_micros: AtomicU64,
+    max_run_micros: AtomicU64,
+}
+
+impl Counters {
+    fn record(&self, wait: Duration, run: Duration) {
+        let run = run.as_micros() as u64;
+        self.completed.fetch_add(1, Ordering::Relaxed);
+        self.wait_micros
+            .fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+        self.run_micros.fetch_add(run, Ordering::Relaxed);
+        self.max_run_micros.fetch_max(run, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of the queue depth and job latencies since start up
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SigningPoolStats {
+    pub workers: usize,
+    pub capacity: usize,
+    pub queued: usize,
+    pub running: usize,
+    pub completed: u64,
+    pub rejected: u64,
+    /// Mean time jobs spent waiting in the queue
+    pub mean_wait_ms: f64,
+    /// Mean time spent signing or verifying
+    pub mean_latency_ms: f64,
+    pub max_latency_ms: f64,
+}
+
+/// Handle to the pool, clones share the same threads and queue
+#[derive(Clone)]
+pub struct SigningPool {
+    jobs: SyncSender<Job>,
+    counters: Arc<Counters>,
+    workers: usize,
+    capacity: usize,
+    retry_after: Duration,
+}
+
+impl SigningPool {
+    /// Start `workers` threads sharing a queue of `capacity` jobs
+    ///
+    /// Clients turned away because of a full queue are asked to retry after
+    /// `retry_after`.
+    ///
+    /// Panics if `workers` or `capacity` is zero.
+    pub fn new(workers: usize, capacity: usize, retry_after: Duration) -> Self {
+        assert!(workers > 0, "Signing pool needs at least one worker");
+        assert!(
+            capacity > 0,
+            "Signing queue needs room for at least one job"
+        );
+
+        let (jobs, queue) = mpsc::sync_channel::<Job>(capacity);
+        let queue = Arc::new(Mutex::new(queue));
+
+        for worker in 0..workers {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(format!("signing-{}", worker))
+                .spawn(move || run_worker(queue))
+                .expect("Failed to spawn signing worker");
+        }
+
+        Self {
+            jobs,
+            counters: Arc::new(Counters::default()),
+            workers,
+            capacity,
+            retry_after,
+        }
+    }
+
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    /// Run `job` on the pool, fails right away if the queue is full
+    pub async fn execute<F, T>(&self, job: F) -> Result<T, SigningPoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.submit(job)?
+            .await
+            .map_err(|_| SigningPoolError::Failed)
+    }
+
+    /// Queue `job` without waiting for it, result is sent to the returned receiver
+    pub fn submit<F, T>(&self, job: F) -> Result<oneshot::Receiver<T>, SigningPoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        let job = self.wrap(job, move |res| {
+            let _ = reply.send(res);
+        });
+
+        match self.jobs.try_send(job) {
+            Ok(()) => Ok(result),
+            Err(TrySendError::Full(_)) => {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                warn!("Signing queue is full, turning request away");
+                Err(SigningPoolError::Busy {
+                    retry_after: self.retry_after,
+                })
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                Err(SigningPoolError::Failed)
+            }
+        }
+    }
+
+    /// Run `job` on the pool and wait for it, waits for room in the queue
+    ///
+    /// Meant for work that was already admitted, e.g. signing a request whose
+    /// bundler signature was verified. Must not be called from async code.
+    pub fn execute_blocking<F, T>(&self, job: F) -> Result<T, SigningPoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (reply, result) = mpsc::sync_channel(1);
+        let job = self.wrap(job, move |res| {
+            let _ = reply.send(res);
+        });
+
+        if self.jobs.send(job).is_err() {
+            self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(SigningPoolError::Failed);
+        }
+
+        result.recv().map_err(|_| SigningPoolError::Failed)
+    }
+
+    pub fn stats(&self) -> SigningPoolStats {
+        let counters = &self.counters;
+        let completed = counters.completed.load(Ordering::Relaxed);
+        let mean_ms = |micros: &AtomicU64| {
+            if completed == 0 {
+                0.0
+            } else {
+                micros.load(Ordering::Relaxed) as f64 / completed as f64 / 1000.0
+            }
+        };
+
+        SigningPoolStats {
+            workers: self.workers,
+            capacity: self.capacity,
+            queued: counters.queued.load(Ordering::Relaxed),
+            running: counters.running.load(Ordering::Relaxed),
+            completed,
+            rejected: counters.rejected.load(Ordering::Relaxed),
+            mean_wait_ms: mean_ms(&counters.wait_micros),
+            mean_latency_ms: mean_ms(&counters.run_micros),
+            max_latency_ms: counters.max_run_micros.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+
+    /// Wrap `job` so that it updates the counters and hands its result to `reply`
+    ///
+    /// Job counts as queued from here on.
+    fn wrap<F, T, R>(&self, job: F, reply: R) -> Job
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+        R: FnOnce(T) + Send + 'static,
+    {
+        let counters = self.counters.clone();
+        let queued_at = Instant::now();
+        counters.queued.fetch_add(1, Ordering::Relaxed);
+
+        Box::new(move || {
+            counters.queued.fetch_sub(1, Ordering::Relaxed);
+            counters.running.fetch_add(1, Ordering::Relaxed);
+            let started_at = Instant::now();
+
+            // Panicking job drops the reply, the caller sees a failed job
+            let res = panic::catch_unwind(AssertUnwindSafe(job));
+
+            counters.running.fetch_sub(1, Ordering::Relaxed);
+            counters.record(started_at - queued_at, started_at.elapsed());
+            match res {
+                Ok(res) => reply(res),
+                Err(_) => error!("Signing job panicked"),
+            }
+        })
+    }
+}
+
+fn run_worker(queue: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        // Lock is released before running the job
+        let job = queue.lock().unwrap().recv();
+        match job {
+            Ok(job) => job(),
+            // Pool was dropped
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::{SigningPool, SigningPoolError};
+
+    #[actix_rt::test]
+    async fn job_result_is_returned() {
+        let pool = SigningPool::new(2, 4, Duration::from_secs(1));
+
+        assert_eq!(pool.execute(|| 2 + 2).await.unwrap(), 4);
+        assert_eq!(pool.execute_blocking(|| 3 * 3).unwrap(), 9);
+
+        let stats = pool.stats();
+        assert_eq!(stats.completed, 2);
+        assert_eq!(stats.queued, 0);
+        assert_eq!(stats.rejected, 0);
+    }
+
+    #[actix_rt::test]
+    async fn full_queue_turns_jobs_away() {
+        let pool = SigningPool::new(1, 1, Duration::from_secs(3));
+
+        // Occupy the only worker, then fill the queue
+        let (release, blocked) = mpsc::channel::<()>();
+        let (started, running) = mpsc::channel();
+        let busy = pool
+            .submit(move || {
+                started.send(()).unwrap();
+                blocked.recv().unwrap();
+            })
+            .unwrap();
+        running.recv_timeout(Duration::from_secs(5)).unwrap();
+        let queued = pool.submit(|| ()).unwrap();
+
+        match pool.execute(|| ()).await {
+            Err(SigningPoolError::Busy { retry_after }) => {
+                assert_eq!(retry_after, Duration::from_secs(3))
+            }
+            _ => panic!("expected job to be turned away"),
+        }
+        assert_eq!(pool.stats().queued, 1);
+        assert_eq!(pool.stats().rejected, 1);
+
+        release.send(()).unwrap();
+        busy.await.unwrap();
+        queued.await.unwrap();
+        assert_eq!(pool.stats().completed, 2);
+        assert!(pool.execute(|| ()).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn panicking_job_fails_without_killing_worker() {
+        let pool = SigningPool::new(1, 1, Duration::from_secs(1));
+
+        assert!(matches!(
+            pool.execute(|| panic!("boom")).await,
+            Err(SigningPoolError::Failed)
+        ));
+        assert_eq!(pool.execute(|| 1).await.unwrap(), 1);
+    }
+}